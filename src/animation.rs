@@ -0,0 +1,100 @@
+use tetra::graphics::{DrawParams, Rectangle, Texture};
+use tetra::math::Vec2;
+use tetra::Context;
+
+// one picture from a sprite sheet and how long it stays up, in seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub index: usize,
+    pub duration: f32,
+}
+
+// frames laid out left to right in a single row, all the same size
+#[derive(Clone)]
+pub struct SpriteSheet {
+    texture: Texture,
+    frame_size: Vec2<f32>,
+}
+
+impl SpriteSheet {
+    pub fn new(texture: Texture, frame_width: f32, frame_height: f32) -> SpriteSheet {
+        SpriteSheet {
+            texture,
+            frame_size: Vec2::new(frame_width, frame_height),
+        }
+    }
+
+    pub fn region(&self, index: usize) -> Rectangle {
+        Rectangle::new(index as f32 * self.frame_size.x, 0.0, self.frame_size.x, self.frame_size.y)
+    }
+
+    // centred on position, mirrored for things facing left since the sheets all face right
+    pub fn draw(&self, ctx: &mut Context, index: usize, position: Vec2<f32>, scale: f32, flip: bool) {
+        let scale_x = if flip { -scale } else { scale };
+        self.texture.draw_region(
+            ctx,
+            self.region(index),
+            DrawParams::new()
+                .position(position)
+                .origin(self.frame_size / 2.0)
+                .scale(Vec2::new(scale_x, scale)),
+        );
+    }
+}
+
+// plays a list of frames, advanced by the simulation's dt so it stays in step with replays.
+// a clip that doesn't loop holds its last frame once it's done
+#[derive(Debug, Clone)]
+pub struct Animation {
+    frames: &'static [Frame],
+    looping: bool,
+    current: usize,
+    elapsed: f32,
+}
+
+impl Animation {
+    pub fn new(frames: &'static [Frame], looping: bool) -> Animation {
+        assert!(!frames.is_empty(), "an animation needs at least one frame");
+        assert!(frames.iter().all(|frame| frame.duration > 0.0), "animation frames need a duration above zero");
+        Animation {
+            frames,
+            looping,
+            current: 0,
+            elapsed: 0.0,
+        }
+    }
+
+    // switches clip and starts it from the top, unless it's already the one playing
+    pub fn play(&mut self, frames: &'static [Frame], looping: bool) {
+        if !std::ptr::eq(self.frames, frames) {
+            *self = Animation::new(frames, looping);
+        }
+    }
+
+    // a long dt can skip several frames, and a speed multiplier just means passing a scaled dt
+    pub fn update(&mut self, dt: f32) {
+        self.elapsed += dt;
+        while self.elapsed >= self.frames[self.current].duration {
+            if self.current + 1 == self.frames.len() {
+                if !self.looping {
+                    self.elapsed = self.frames[self.current].duration;
+                    return;
+                }
+                self.elapsed -= self.frames[self.current].duration;
+                self.current = 0;
+            } else {
+                self.elapsed -= self.frames[self.current].duration;
+                self.current += 1;
+            }
+        }
+    }
+
+    // the sprite sheet index to draw
+    pub fn index(&self) -> usize {
+        self.frames[self.current].index
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.current + 1 == self.frames.len() && self.elapsed >= self.frames[self.current].duration
+    }
+}
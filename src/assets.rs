@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use tetra::audio::Sound;
+use tetra::graphics::text::Font;
+use tetra::graphics::Texture;
+use tetra::{Context, TetraError};
+
+// every texture, font and sound the game uses, loaded once up front. handing one out is only a clone of
+// tetra's reference counted handle, and if anything is missing the error lists all of it at once
+pub struct Assets {
+    textures: HashMap<String, Texture>,
+    fonts: HashMap<String, Font>,
+    sounds: HashMap<String, Sound>,
+}
+
+impl Assets {
+    // names are relative to dir, e.g. "token.png"
+    pub fn load<P: AsRef<Path>>(ctx: &mut Context, dir: P, textures: &[&str], fonts: &[&str], sounds: &[&str]) -> tetra::Result<Assets> {
+        let dir = dir.as_ref();
+        let mut assets = Assets {
+            textures: HashMap::new(),
+            fonts: HashMap::new(),
+            sounds: HashMap::new(),
+        };
+        let mut failed: Vec<(PathBuf, TetraError)> = Vec::new();
+
+        for &name in textures {
+            if assets.textures.contains_key(name) {
+                continue;
+            }
+            let path = dir.join(name);
+            match Texture::new(ctx, &path) {
+                Ok(texture) => {
+                    assets.textures.insert(name.to_string(), texture);
+                }
+                Err(e) => failed.push((path, e)),
+            }
+        }
+
+        for &name in fonts {
+            if assets.fonts.contains_key(name) {
+                continue;
+            }
+            let path = dir.join(name);
+            match Font::bmfont(ctx, &path) {
+                Ok(font) => {
+                    assets.fonts.insert(name.to_string(), font);
+                }
+                Err(e) => failed.push((path, e)),
+            }
+        }
+
+        // only reads the file, a sound that won't decode shows up when it's first played
+        for &name in sounds {
+            if assets.sounds.contains_key(name) {
+                continue;
+            }
+            let path = dir.join(name);
+            match Sound::new(&path) {
+                Ok(sound) => {
+                    assets.sounds.insert(name.to_string(), sound);
+                }
+                Err(e) => failed.push((path, e)),
+            }
+        }
+
+        if failed.is_empty() {
+            return Ok(assets);
+        }
+        let mut message = format!("couldn't load {} asset(s):", failed.len());
+        for (path, e) in &failed {
+            message.push_str(&format!("\n  {}: {}", path.display(), Assets::reason(e)));
+        }
+        Err(TetraError::PlatformError(message))
+    }
+
+    // only asks for names that were passed to load, so a miss is a bug rather than a missing file
+    pub fn texture(&self, name: &str) -> Texture {
+        match self.textures.get(name) {
+            Some(texture) => texture.clone(),
+            None => panic!("texture {} was never loaded, add it to the list passed to Assets::load", name),
+        }
+    }
+
+    pub fn font(&self, name: &str) -> Font {
+        match self.fonts.get(name) {
+            Some(font) => font.clone(),
+            None => panic!("font {} was never loaded, add it to the list passed to Assets::load", name),
+        }
+    }
+
+    pub fn sound(&self, name: &str) -> Sound {
+        match self.sounds.get(name) {
+            Some(sound) => sound.clone(),
+            None => panic!("sound {} was never loaded, add it to the list passed to Assets::load", name),
+        }
+    }
+
+    // tetra's own message for a missing file only says which file, and we print that already
+    fn reason(e: &TetraError) -> String {
+        match e {
+            TetraError::FailedToLoadAsset { reason, .. } => reason.to_string(),
+            other => other.to_string(),
+        }
+    }
+}
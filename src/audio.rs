@@ -0,0 +1,289 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tetra::audio::{SoundInstance, SoundState};
+use tetra::Context;
+
+use super::assets::Assets;
+use super::config;
+use super::simulation::GameEvent;
+
+// relative to the resources folder, like the textures
+pub const MUSIC: &str = "sounds/music.wav";
+
+// the short sounds that go with something happening in the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    Jump,
+    Land,
+    Token,
+    BatHit,
+    Death,
+}
+
+impl Effect {
+    pub const ALL: [Effect; 5] = [Effect::Jump, Effect::Land, Effect::Token, Effect::BatHit, Effect::Death];
+
+    pub const fn file(self) -> &'static str {
+        match self {
+            Effect::Jump => "sounds/jump.wav",
+            Effect::Land => "sounds/land.wav",
+            Effect::Token => "sounds/token.wav",
+            Effect::BatHit => "sounds/bat_hit.wav",
+            Effect::Death => "sounds/death.wav",
+        }
+    }
+
+    pub fn for_event(event: &GameEvent) -> Effect {
+        match event {
+            GameEvent::TokenCollected { .. } => Effect::Token,
+            GameEvent::PlayerHurt { .. } => Effect::BatHit,
+            GameEvent::PlayerDied { .. } => Effect::Death,
+            GameEvent::Jumped { .. } => Effect::Jump,
+            GameEvent::Landed { .. } => Effect::Land,
+        }
+    }
+
+    // a bat hit goes off every tick one is touching, so it plays out instead of starting over
+    pub fn restarts(self) -> bool {
+        self != Effect::BatHit
+    }
+}
+
+// 0 is silent and 1 is as loud as the file. any field missing from the file keeps the value below
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioSettings {
+    pub music_volume: f32,
+    pub effects_volume: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            music_volume: 0.5,
+            effects_volume: 0.8,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn load<P: AsRef<Path>>(path: P) -> tetra::Result<AudioSettings> {
+        let path = path.as_ref();
+        let settings: AudioSettings = config::load_toml(path, "audio settings")?;
+        for (name, volume) in [("music_volume", settings.music_volume), ("effects_volume", settings.effects_volume)] {
+            if !(0.0..=1.0).contains(&volume) {
+                let message = format!("{} has to be between 0 and 1, not {}", name, volume);
+                return Err(config::invalid(path, "audio settings", message));
+            }
+        }
+        Ok(settings)
+    }
+}
+
+// whatever actually makes the noise. volumes are already scaled by the settings
+pub trait AudioBackend {
+    // an effect that's still playing starts over if restart is set, otherwise it's left to finish
+    fn play(&mut self, effect: Effect, volume: f32, restart: bool);
+    // loops until paused, then carries on from where it stopped
+    fn play_music(&mut self, volume: f32);
+    fn pause_music(&mut self);
+}
+
+// plays nothing, for headless runs and machines without an audio device
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullAudio;
+
+impl AudioBackend for NullAudio {
+    fn play(&mut self, _effect: Effect, _volume: f32, _restart: bool) {}
+    fn play_music(&mut self, _volume: f32) {}
+    fn pause_music(&mut self) {}
+}
+
+// one instance per sound made up front and restarted each time, rather than a new one per play
+pub struct TetraAudio {
+    effects: Vec<(Effect, SoundInstance)>,
+    music: SoundInstance,
+}
+
+impl TetraAudio {
+    // the sounds have to have been passed to Assets::load
+    pub fn new(ctx: &Context, assets: &Assets) -> tetra::Result<TetraAudio> {
+        let mut effects = Vec::new();
+        for effect in Effect::ALL {
+            effects.push((effect, assets.sound(effect.file()).spawn(ctx)?));
+        }
+        let music = assets.sound(MUSIC).spawn(ctx)?;
+        music.set_repeating(true);
+        Ok(TetraAudio { effects, music })
+    }
+}
+
+impl AudioBackend for TetraAudio {
+    fn play(&mut self, effect: Effect, volume: f32, restart: bool) {
+        let Some((_, instance)) = self.effects.iter().find(|(e, _)| *e == effect) else {
+            return;
+        };
+        if instance.state() == SoundState::Playing {
+            if !restart {
+                return;
+            }
+            instance.stop();
+        }
+        instance.set_volume(volume);
+        instance.play();
+    }
+
+    fn play_music(&mut self, volume: f32) {
+        self.music.set_volume(volume);
+        self.music.play();
+    }
+
+    fn pause_music(&mut self) {
+        self.music.pause();
+    }
+}
+
+impl Drop for TetraAudio {
+    // a repeating instance that's dropped while playing never stops
+    fn drop(&mut self) {
+        self.music.stop();
+    }
+}
+
+// what the game talks to: turns simulation events into sounds and applies the volume settings
+pub struct Audio {
+    backend: Box<dyn AudioBackend>,
+    settings: AudioSettings,
+}
+
+impl Audio {
+    pub fn new(backend: Box<dyn AudioBackend>, settings: AudioSettings) -> Audio {
+        Audio { backend, settings }
+    }
+
+    // tetra's backend, or the null one when muted or when there's nothing to play on
+    pub fn open(ctx: &Context, assets: &Assets, settings: AudioSettings, muted: bool) -> Audio {
+        if muted {
+            return Audio::new(Box::new(NullAudio), settings);
+        }
+        match TetraAudio::new(ctx, assets) {
+            Ok(backend) => Audio::new(Box::new(backend), settings),
+            Err(e) => {
+                eprintln!("no sound: {}", e);
+                Audio::new(Box::new(NullAudio), settings)
+            }
+        }
+    }
+
+    pub fn handle(&mut self, events: &[GameEvent]) {
+        for event in events {
+            self.play(Effect::for_event(event));
+        }
+    }
+
+    pub fn play(&mut self, effect: Effect) {
+        self.backend.play(effect, self.settings.effects_volume, effect.restarts());
+    }
+
+    pub fn play_music(&mut self) {
+        self.backend.play_music(self.settings.music_volume);
+    }
+
+    pub fn pause_music(&mut self) {
+        self.backend.pause_music();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use tetra::math::Vec2;
+
+    use crate::simulation::GameEvent;
+
+    use super::{Audio, AudioBackend, AudioSettings, Effect};
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Call {
+        Play(Effect, f32, bool),
+        PlayMusic(f32),
+        PauseMusic,
+    }
+
+    // keeps a shared log so the test can still read it once the audio owns the backend
+    struct Recording(Rc<RefCell<Vec<Call>>>);
+
+    impl AudioBackend for Recording {
+        fn play(&mut self, effect: Effect, volume: f32, restart: bool) {
+            self.0.borrow_mut().push(Call::Play(effect, volume, restart));
+        }
+
+        fn play_music(&mut self, volume: f32) {
+            self.0.borrow_mut().push(Call::PlayMusic(volume));
+        }
+
+        fn pause_music(&mut self) {
+            self.0.borrow_mut().push(Call::PauseMusic);
+        }
+    }
+
+    fn recorded(settings: AudioSettings) -> (Audio, Rc<RefCell<Vec<Call>>>) {
+        let calls = Rc::new(RefCell::new(Vec::new()));
+        (Audio::new(Box::new(Recording(Rc::clone(&calls))), settings), calls)
+    }
+
+    #[test]
+    fn events_play_their_effects() {
+        let settings = AudioSettings {
+            music_volume: 0.25,
+            effects_volume: 0.75,
+        };
+        let (mut audio, calls) = recorded(settings);
+        let position = Vec2::zero();
+        audio.handle(&[
+            GameEvent::Jumped { position },
+            GameEvent::Landed { position },
+            GameEvent::TokenCollected { index: 3, position },
+            GameEvent::PlayerHurt { position },
+            GameEvent::PlayerHurt { position },
+            GameEvent::PlayerDied { position },
+        ]);
+        assert_eq!(
+            *calls.borrow(),
+            [
+                Call::Play(Effect::Jump, 0.75, true),
+                Call::Play(Effect::Land, 0.75, true),
+                Call::Play(Effect::Token, 0.75, true),
+                Call::Play(Effect::BatHit, 0.75, false),
+                Call::Play(Effect::BatHit, 0.75, false),
+                Call::Play(Effect::Death, 0.75, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn music_uses_its_own_volume() {
+        let settings = AudioSettings {
+            music_volume: 0.25,
+            effects_volume: 0.75,
+        };
+        let (mut audio, calls) = recorded(settings);
+        audio.play_music();
+        audio.pause_music();
+        audio.play(Effect::Jump);
+        assert_eq!(
+            *calls.borrow(),
+            [Call::PlayMusic(0.25), Call::PauseMusic, Call::Play(Effect::Jump, 0.75, true)]
+        );
+    }
+
+    #[test]
+    fn nothing_plays_without_events() {
+        let (mut audio, calls) = recorded(AudioSettings::default());
+        audio.handle(&[]);
+        assert!(calls.borrow().is_empty());
+    }
+}
@@ -0,0 +1,28 @@
+use std::time::Instant;
+
+use super::difficulty::Difficulty;
+use super::flock_config::FlockConfig;
+use super::simulation::{InputFrame, Simulation};
+use super::TICK_RATE;
+
+const BAT_COUNTS: [usize; 3] = [100, 1_000, 10_000];
+const WARMUP_TICKS: usize = 10;
+const MEASURED_TICKS: usize = 100;
+
+// steps a headless simulation at a few swarm sizes and prints the average cost of a tick
+pub fn run_boid_benchmark(seed: u64, flock_config: &FlockConfig) {
+    let inputs = InputFrame::default();
+    for &bats in &BAT_COUNTS {
+        let mut simulation = Simulation::new(seed, flock_config.clone(), Difficulty::constant(bats), TICK_RATE, None);
+        for _ in 0..WARMUP_TICKS {
+            simulation.step(&inputs);
+        }
+
+        let start = Instant::now();
+        for _ in 0..MEASURED_TICKS {
+            simulation.step(&inputs);
+        }
+        let per_tick = start.elapsed() / MEASURED_TICKS as u32;
+        println!("{:>6} bats: {:>10.3} ms/tick", bats, per_tick.as_secs_f64() * 1000.0);
+    }
+}
@@ -0,0 +1,110 @@
+use tetra::math::{Mat4, Vec2, Vec3};
+
+use super::collision::Aabb;
+
+// trauma drains at this much per second, shake grows with its square so small knocks stay subtle
+const TRAUMA_DECAY: f32 = 1.2;
+// in pixels at full trauma
+const MAX_SHAKE: f32 = 12.0;
+// radians per second of the slowest wobble in the shake
+const SHAKE_FREQUENCY: f32 = 18.0;
+
+// which part of the world is on screen. position is the top left of the view in world coordinates
+// and drawing goes through transform, so nothing in the world has to move for the view to.
+// it only follows vertically: the world is never wider than the window
+#[derive(Debug, Clone)]
+pub struct Camera {
+    position: Vec2<f32>,
+    prev_position: Vec2<f32>,
+    size: Vec2<f32>,
+    // relative to the view, the target can move around in here without the camera following
+    dead_zone: Aabb,
+    // the view has to stay inside this, None for a world with no edges
+    bounds: Option<Aabb>,
+    // the bottom of the view never goes lower than this. scrolling raises it, so the view only
+    // ever moves up and anything that drops out of the bottom is gone
+    floor: f32,
+    // 0 to 1, how hard the screen is shaking
+    trauma: f32,
+    // seconds, drives the shake so it's the same every time a replay is played
+    time: f32,
+}
+
+impl Camera {
+    pub fn new(position: Vec2<f32>, size: Vec2<f32>, dead_zone: Aabb, bounds: Option<Aabb>) -> Camera {
+        let mut camera = Camera {
+            position,
+            prev_position: position,
+            size,
+            dead_zone,
+            bounds,
+            floor: position.y + size.y,
+            trauma: 0.0,
+            time: 0.0,
+        };
+        camera.clamp();
+        camera.prev_position = camera.position;
+        camera
+    }
+
+    pub fn view(&self) -> Aabb {
+        Aabb::new(self.position, self.size)
+    }
+
+    // once a tick: scrolls up by scroll pixels, then follows target if it's left the dead zone
+    pub fn update(&mut self, target: Vec2<f32>, scroll: f32, dt: f32) {
+        self.prev_position = self.position;
+        self.floor -= scroll;
+        self.position.y = self.position.y.min(self.floor - self.size.y);
+
+        let (top, bottom) = (self.position.y + self.dead_zone.min().y, self.position.y + self.dead_zone.max().y);
+        if target.y < top {
+            self.position.y -= top - target.y;
+        } else if target.y > bottom {
+            self.position.y += target.y - bottom;
+        }
+        self.clamp();
+
+        self.trauma = (self.trauma - TRAUMA_DECAY * dt).max(0.0);
+        self.time += dt;
+    }
+
+    fn clamp(&mut self) {
+        self.position.y = self.position.y.min(self.floor - self.size.y);
+        if let Some(bounds) = self.bounds {
+            // a view bigger than the bounds keeps its top left on theirs
+            self.position.x = self.position.x.min(bounds.max().x - self.size.x).max(bounds.min().x);
+            self.position.y = self.position.y.min(bounds.max().y - self.size.y).max(bounds.min().y);
+            // scrolling stops at the top
+            self.floor = self.floor.max(bounds.min().y + self.size.y);
+        }
+        self.floor = self.floor.min(self.position.y + self.size.y);
+    }
+
+    // adds up to a maximum of 1
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+
+    // a few sine waves at unrelated frequencies, smooth but never quite repeating
+    fn noise(t: f32, phase: f32) -> f32 {
+        (t + phase).sin() * 0.5 + (t * 2.3 + phase * 1.7).sin() * 0.3 + (t * 4.1 + phase * 0.6).sin() * 0.2
+    }
+
+    fn shake(&self) -> Vec2<f32> {
+        let amount = MAX_SHAKE * self.trauma * self.trauma;
+        let t = self.time * SHAKE_FREQUENCY;
+        Vec2::new(Camera::noise(t, 0.0), Camera::noise(t, 11.3)) * amount
+    }
+
+    // for graphics::set_transform_matrix, blend is how far we are between the last tick and the next
+    pub fn transform(&self, blend: f32) -> Mat4<f32> {
+        let position = Vec2::lerp(self.prev_position, self.position, blend) + self.shake();
+        Mat4::translation_3d(Vec3::new(-position.x.round(), -position.y.round(), 0.0))
+    }
+
+    // ignores the shake, it's only ever used for clicking on things
+    pub fn screen_to_world(&self, point: Vec2<f32>) -> Vec2<f32> {
+        point + self.position
+    }
+}
@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+use tetra::math::Vec2;
+
+// axis aligned box, position is the top left corner like everywhere else in the game
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Aabb {
+    pub position: Vec2<f32>,
+    pub size: Vec2<f32>,
+}
+
+// where along a sweep two boxes first touch, time is a fraction of the displacement (0 to 1)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    pub time: f32,
+    pub normal: Vec2<f32>,
+}
+
+impl Aabb {
+    pub fn new(position: Vec2<f32>, size: Vec2<f32>) -> Aabb {
+        Aabb { position, size }
+    }
+
+    pub fn min(&self) -> Vec2<f32> {
+        self.position
+    }
+
+    pub fn max(&self) -> Vec2<f32> {
+        self.position + self.size
+    }
+
+    pub fn center(&self) -> Vec2<f32> {
+        self.position + self.size / 2.0
+    }
+
+    pub fn translated(&self, offset: Vec2<f32>) -> Aabb {
+        Aabb::new(self.position + offset, self.size)
+    }
+
+    // grown by margin on every side
+    pub fn expanded(&self, margin: f32) -> Aabb {
+        Aabb::new(self.position - margin, self.size + margin * 2.0)
+    }
+
+    pub fn contains_point(&self, point: Vec2<f32>) -> bool {
+        point.x >= self.min().x && point.x <= self.max().x && point.y >= self.min().y && point.y <= self.max().y
+    }
+
+    // boxes that only share an edge don't count, otherwise standing on a platform would be a collision
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min().x < other.max().x
+            && self.max().x > other.min().x
+            && self.min().y < other.max().y
+            && self.max().y > other.min().y
+    }
+
+    // the shortest move that takes self back out of other, if they overlap
+    pub fn penetration(&self, other: &Aabb) -> Option<Vec2<f32>> {
+        if !self.overlaps(other) {
+            return None;
+        }
+        let left = other.min().x - self.max().x;
+        let right = other.max().x - self.min().x;
+        let up = other.min().y - self.max().y;
+        let down = other.max().y - self.min().y;
+
+        let x = if -left < right { left } else { right };
+        let y = if -up < down { up } else { down };
+        if x.abs() < y.abs() {
+            Some(Vec2::new(x, 0.0))
+        } else {
+            Some(Vec2::new(0.0, y))
+        }
+    }
+
+    // continuous test for self moving by displacement against a still other. boxes that already
+    // overlap don't report a hit, push them apart with penetration first
+    pub fn sweep(&self, displacement: Vec2<f32>, other: &Aabb) -> Option<Hit> {
+        if self.overlaps(other) {
+            return None;
+        }
+
+        let (x_entry, x_exit) = Aabb::axis_times(self.min().x, self.max().x, other.min().x, other.max().x, displacement.x)?;
+        let (y_entry, y_exit) = Aabb::axis_times(self.min().y, self.max().y, other.min().y, other.max().y, displacement.y)?;
+
+        let entry = x_entry.max(y_entry);
+        let exit = x_exit.min(y_exit);
+        if entry >= exit || !(0.0..=1.0).contains(&entry) {
+            return None;
+        }
+
+        let normal = if x_entry > y_entry {
+            Vec2::new(-displacement.x.signum(), 0.0)
+        } else {
+            Vec2::new(0.0, -displacement.y.signum())
+        };
+        Some(Hit { time: entry, normal })
+    }
+
+    // when along the sweep the two ranges start and stop overlapping on one axis
+    fn axis_times(min: f32, max: f32, other_min: f32, other_max: f32, delta: f32) -> Option<(f32, f32)> {
+        if delta > 0.0 {
+            Some(((other_min - max) / delta, (other_max - min) / delta))
+        } else if delta < 0.0 {
+            Some(((other_max - min) / delta, (other_min - max) / delta))
+        } else if max <= other_min || min >= other_max {
+            // not moving on this axis and never overlapping on it
+            None
+        } else {
+            Some((f32::NEG_INFINITY, f32::INFINITY))
+        }
+    }
+
+    // overlapping now or touching at some point during the move
+    pub fn touches_during(&self, displacement: Vec2<f32>, other: &Aabb) -> bool {
+        self.overlaps(other) || self.sweep(displacement, other).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tetra::math::Vec2;
+
+    use super::{Aabb, Hit};
+
+    fn aabb(x: f32, y: f32, width: f32, height: f32) -> Aabb {
+        Aabb::new(Vec2::new(x, y), Vec2::new(width, height))
+    }
+
+    #[test]
+    fn resting_flush_hits_straight_away() {
+        let player = aabb(0.0, 40.0, 10.0, 10.0);
+        let platform = aabb(-20.0, 50.0, 50.0, 10.0);
+        assert!(!player.overlaps(&platform));
+        assert_eq!(
+            player.sweep(Vec2::new(0.0, 5.0), &platform),
+            Some(Hit { time: 0.0, normal: Vec2::new(0.0, -1.0) })
+        );
+        // walking along the top of it isn't a hit
+        assert_eq!(player.sweep(Vec2::new(5.0, 0.0), &platform), None);
+    }
+
+    #[test]
+    fn still_axis_either_always_overlaps_or_never_does() {
+        assert_eq!(Aabb::axis_times(0.0, 10.0, 5.0, 20.0, 0.0), Some((f32::NEG_INFINITY, f32::INFINITY)));
+        // sharing an edge isn't overlapping
+        assert_eq!(Aabb::axis_times(0.0, 10.0, 10.0, 20.0, 0.0), None);
+        assert_eq!(Aabb::axis_times(0.0, 10.0, 15.0, 20.0, 0.0), None);
+
+        // falling past the side of a platform
+        let player = aabb(0.0, 0.0, 10.0, 10.0);
+        assert_eq!(player.sweep(Vec2::new(0.0, 100.0), &aabb(10.0, 20.0, 30.0, 10.0)), None);
+    }
+
+    #[test]
+    fn moving_axis_times() {
+        assert_eq!(Aabb::axis_times(0.0, 10.0, 20.0, 30.0, 20.0), Some((0.5, 1.5)));
+        assert_eq!(Aabb::axis_times(20.0, 30.0, 0.0, 10.0, -20.0), Some((0.5, 1.5)));
+    }
+
+    // hitting a corner exactly lands on top rather than against the side
+    #[test]
+    fn corner_tie_picks_the_y_normal() {
+        let player = aabb(0.0, 0.0, 10.0, 10.0);
+        let platform = aabb(20.0, 20.0, 10.0, 10.0);
+        assert_eq!(
+            player.sweep(Vec2::new(20.0, 20.0), &platform),
+            Some(Hit { time: 0.5, normal: Vec2::new(0.0, -1.0) })
+        );
+        assert_eq!(
+            player.sweep(Vec2::new(20.0, -20.0), &aabb(20.0, -20.0, 10.0, 10.0)),
+            Some(Hit { time: 0.5, normal: Vec2::new(0.0, 1.0) })
+        );
+    }
+
+    #[test]
+    fn sweep_misses() {
+        let player = aabb(0.0, 0.0, 10.0, 10.0);
+        let platform = aabb(0.0, 30.0, 10.0, 10.0);
+        // not far enough, moving away, and already overlapping
+        assert_eq!(player.sweep(Vec2::new(0.0, 10.0), &platform), None);
+        assert_eq!(player.sweep(Vec2::new(0.0, -50.0), &platform), None);
+        assert_eq!(player.sweep(Vec2::new(0.0, 50.0), &aabb(5.0, 5.0, 10.0, 10.0)), None);
+    }
+
+    #[test]
+    fn penetration_takes_the_shallower_axis() {
+        let player = aabb(0.0, 0.0, 10.0, 10.0);
+        assert_eq!(player.penetration(&aabb(8.0, 5.0, 20.0, 20.0)), Some(Vec2::new(-2.0, 0.0)));
+        assert_eq!(player.penetration(&aabb(5.0, 8.0, 20.0, 20.0)), Some(Vec2::new(0.0, -2.0)));
+        assert_eq!(player.penetration(&aabb(-18.0, -15.0, 20.0, 20.0)), Some(Vec2::new(2.0, 0.0)));
+        assert_eq!(player.penetration(&aabb(-15.0, -18.0, 20.0, 20.0)), Some(Vec2::new(0.0, 2.0)));
+        assert_eq!(player.penetration(&aabb(10.0, 0.0, 10.0, 10.0)), None);
+    }
+
+    // the whole point of sweeping: a fast fall can't skip over a platform between two ticks
+    #[test]
+    fn fast_box_does_not_tunnel() {
+        let player = aabb(0.0, 0.0, 10.0, 10.0);
+        let platform = aabb(-20.0, 30.0, 50.0, 16.0);
+        let displacement = Vec2::new(0.0, 60.0);
+        assert!(!player.translated(displacement).overlaps(&platform));
+
+        let hit = player.sweep(displacement, &platform).unwrap();
+        assert!((hit.time - 20.0 / 60.0).abs() < 1e-6, "time {}", hit.time);
+        assert_eq!(hit.normal, Vec2::new(0.0, -1.0));
+        assert!(player.touches_during(displacement, &platform));
+    }
+
+    #[test]
+    fn touches_during_counts_overlaps_and_sweeps() {
+        let player = aabb(0.0, 0.0, 10.0, 10.0);
+        assert!(player.touches_during(Vec2::zero(), &aabb(5.0, 5.0, 10.0, 10.0)));
+        assert!(player.touches_during(Vec2::new(100.0, 0.0), &aabb(50.0, 0.0, 5.0, 5.0)));
+        assert!(!player.touches_during(Vec2::new(100.0, 0.0), &aabb(50.0, 20.0, 5.0, 5.0)));
+    }
+}
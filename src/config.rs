@@ -0,0 +1,66 @@
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use tetra::TetraError;
+
+// reads a toml file into T. what names the kind of file in errors, e.g. "flock config"
+pub fn load_toml<T: DeserializeOwned>(path: &Path, what: &str) -> tetra::Result<T> {
+    let text = fs::read_to_string(path).map_err(|reason| TetraError::FailedToLoadAsset {
+        reason,
+        path: path.to_path_buf(),
+    })?;
+    toml::from_str(&text).map_err(|e| invalid(path, what, e))
+}
+
+// for whatever a file gets wrong that the format can't catch, worded the same as a parse error
+pub fn invalid(path: &Path, what: &str, message: impl Display) -> TetraError {
+    TetraError::PlatformError(format!("invalid {} {}: {}", what, path.display(), message))
+}
+
+// a path given on the command line has to load, otherwise the bundled file is used if it's
+// there, otherwise the built in defaults
+pub fn load_or_default<T: Default>(path: Option<&Path>, bundled: &str, load: impl FnOnce(&Path) -> tetra::Result<T>) -> tetra::Result<T> {
+    match path {
+        Some(path) => load(path),
+        None if Path::new(bundled).exists() => load(Path::new(bundled)),
+        None => Ok(T::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use tetra::TetraError;
+
+    use crate::flock_config::FlockConfig;
+
+    use super::{load_or_default, load_toml};
+
+    const MISSING: &str = "./resources/not_there.toml";
+
+    #[test]
+    fn falls_back_to_the_defaults() {
+        let config = load_or_default(None, MISSING, |path| FlockConfig::load(path)).unwrap();
+        assert_eq!(config, FlockConfig::default());
+    }
+
+    #[test]
+    fn an_explicit_path_has_to_exist() {
+        let result = load_or_default(Some(Path::new(MISSING)), "./resources/flock.toml", |path| FlockConfig::load(path));
+        assert!(matches!(result, Err(TetraError::FailedToLoadAsset { .. })));
+    }
+
+    #[test]
+    fn parse_errors_name_the_file() {
+        let result = load_toml::<FlockConfig>(Path::new("./resources/difficulty.toml"), "flock config");
+        match result {
+            Err(TetraError::PlatformError(message)) => {
+                assert!(message.starts_with("invalid flock config ./resources/difficulty.toml: "), "{}", message)
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tetra::graphics::text::{Font, Text};
+use tetra::graphics::{Color, DrawParams};
+use tetra::input::{self, Key};
+use tetra::math::Vec2;
+use tetra::Context;
+
+use super::config;
+
+// what the player wants to do, whichever keys they use for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Jump,
+    FastFall,
+    Restart,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 6] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::FastFall,
+        Action::Restart,
+        Action::Pause,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "MOVE LEFT",
+            Action::MoveRight => "MOVE RIGHT",
+            Action::Jump => "JUMP",
+            Action::FastFall => "FAST FALL",
+            Action::Restart => "RESTART",
+            Action::Pause => "PAUSE",
+        }
+    }
+}
+
+// any of an action's keys triggers it. keys are physical positions, so the defaults sit in the
+// same place on azerty or dvorak as they do on qwerty
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Bindings {
+    pub move_left: Vec<Key>,
+    pub move_right: Vec<Key>,
+    pub jump: Vec<Key>,
+    pub fast_fall: Vec<Key>,
+    pub restart: Vec<Key>,
+    pub pause: Vec<Key>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            move_left: vec![Key::A, Key::Left],
+            move_right: vec![Key::D, Key::Right],
+            jump: vec![Key::W, Key::Up],
+            fast_fall: vec![Key::S, Key::Down],
+            restart: vec![Key::R],
+            pause: vec![Key::P],
+        }
+    }
+}
+
+impl Bindings {
+    pub fn load<P: AsRef<Path>>(path: P) -> tetra::Result<Bindings> {
+        config::load_toml(path.as_ref(), "controls")
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+
+    // where rebinding in game saves to, None if the platform has no config directory
+    pub fn user_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("tetras").join("controls.toml"))
+    }
+
+    pub fn keys(&self, action: Action) -> &[Key] {
+        match action {
+            Action::MoveLeft => &self.move_left,
+            Action::MoveRight => &self.move_right,
+            Action::Jump => &self.jump,
+            Action::FastFall => &self.fast_fall,
+            Action::Restart => &self.restart,
+            Action::Pause => &self.pause,
+        }
+    }
+
+    fn keys_mut(&mut self, action: Action) -> &mut Vec<Key> {
+        match action {
+            Action::MoveLeft => &mut self.move_left,
+            Action::MoveRight => &mut self.move_right,
+            Action::Jump => &mut self.jump,
+            Action::FastFall => &mut self.fast_fall,
+            Action::Restart => &mut self.restart,
+            Action::Pause => &mut self.pause,
+        }
+    }
+
+    // for on screen prompts, e.g. "R" or "W/UP", in whatever the keys say on the player's layout
+    pub fn describe(&self, ctx: &Context, action: Action) -> String {
+        match self.keys(action) {
+            [] => "(UNBOUND)".to_string(),
+            keys => keys.iter().map(|&key| key_name(ctx, key).to_uppercase()).collect::<Vec<_>>().join("/"),
+        }
+    }
+
+    pub fn is_down(&self, ctx: &Context, action: Action) -> bool {
+        self.keys(action).iter().any(|&key| input::is_key_down(ctx, key))
+    }
+
+    pub fn is_pressed(&self, ctx: &Context, action: Action) -> bool {
+        self.keys(action).iter().any(|&key| input::is_key_pressed(ctx, key))
+    }
+
+    // adds the key, or takes it off again if the action already had it
+    pub fn toggle(&mut self, action: Action, key: Key) {
+        let keys = self.keys_mut(action);
+        match keys.iter().position(|&bound| bound == key) {
+            Some(i) => {
+                keys.remove(i);
+            }
+            None => keys.push(key),
+        }
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        self.keys_mut(action).clear();
+    }
+}
+
+// what's printed on a physical key in the current layout, so Key::W shows as Z on azerty. a key
+// the layout has no label for falls back to tetra's name for it
+pub fn key_name(ctx: &Context, key: Key) -> String {
+    match input::get_key_label(ctx, key) {
+        Some(label) => label.to_string(),
+        None => format!("{:?}", key),
+    }
+}
+
+// the in game rebinding screen. the keys used to drive it are fixed so it can't be locked out of
+pub struct ControlsScreen {
+    selected: usize,
+    // waiting for the key to add to or remove from the selected action
+    listening: bool,
+    text: Text,
+}
+
+impl ControlsScreen {
+    pub fn new(font: Font) -> ControlsScreen {
+        ControlsScreen {
+            selected: 0,
+            listening: false,
+            text: Text::new("", font),
+        }
+    }
+
+    // true once the player is done with the screen
+    pub fn update(&mut self, ctx: &Context, bindings: &mut Bindings) -> bool {
+        let action = Action::ALL[self.selected];
+        if self.listening {
+            // escape quits the game, so it can't be bound
+            if let Some(&key) = input::get_keys_pressed(ctx).find(|&&key| key != Key::Escape) {
+                bindings.toggle(action, key);
+                self.listening = false;
+            }
+            return false;
+        }
+
+        if input::is_key_pressed(ctx, Key::Up) {
+            self.selected = (self.selected + Action::ALL.len() - 1) % Action::ALL.len();
+        }
+        if input::is_key_pressed(ctx, Key::Down) {
+            self.selected = (self.selected + 1) % Action::ALL.len();
+        }
+        if input::is_key_pressed(ctx, Key::Enter) {
+            self.listening = true;
+        }
+        if input::is_key_pressed(ctx, Key::Backspace) {
+            bindings.clear(action);
+        }
+        input::is_key_pressed(ctx, Key::Tab)
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context, bindings: &Bindings) {
+        let mut content = String::from("CONTROLS\n\n");
+        for (i, &action) in Action::ALL.iter().enumerate() {
+            let keys = bindings.keys(action);
+            let keys = if keys.is_empty() {
+                "(none)".to_string()
+            } else {
+                keys.iter().map(|&key| key_name(ctx, key)).collect::<Vec<_>>().join(", ")
+            };
+            let marker = if i == self.selected { ">" } else { " " };
+            content.push_str(&format!("{} {:<11} {}\n", marker, action.label(), keys));
+        }
+        if self.listening {
+            content.push_str(&format!("\nPRESS A KEY TO ADD TO OR REMOVE FROM\n{}", Action::ALL[self.selected].label()));
+        } else {
+            content.push_str("\nUP/DOWN  CHOOSE\nENTER    ADD OR REMOVE A KEY\nBACKSPACE  CLEAR\nTAB      SAVE AND GO BACK");
+        }
+        self.text.set_content(content);
+        self.text.draw(ctx, DrawParams::new().position(Vec2::new(24.0, 40.0)).color(Color::WHITE));
+    }
+}
@@ -0,0 +1,171 @@
+use tetra::graphics::mesh::{GeometryBuilder, ShapeStyle};
+use tetra::graphics::text::{Font, Text};
+use tetra::graphics::{Color, DrawParams, Texture};
+use tetra::input::{self, Key, MouseButton};
+use tetra::math::Vec2;
+use tetra::Context;
+
+use super::enemy::Enemy;
+use super::simulation::Simulation;
+
+// forces are accelerations and velocities are per second, so scale them to a readable length
+const FORCE_SCALE: f32 = 0.01;
+const VELOCITY_SCALE: f32 = 0.1;
+const PICK_RADIUS: f32 = 16.0;
+
+const RANGE_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.25);
+const VELOCITY_COLOR: Color = Color::BLACK;
+const COHESION_COLOR: Color = Color::rgb(0.0, 0.6, 0.0);
+const SEPARATION_COLOR: Color = Color::rgb(0.9, 0.0, 0.0);
+const ALIGNMENT_COLOR: Color = Color::rgb(0.0, 0.2, 0.9);
+const PLAYER_COLOR: Color = Color::rgb(0.8, 0.0, 0.8);
+const OBSTACLE_COLOR: Color = Color::rgb(1.0, 0.5, 0.0);
+const SELECTED_COLOR: Color = Color::rgb(1.0, 1.0, 1.0);
+
+// F1 toggles it, clicking a bat freezes it and shows its numbers, clicking it again lets it go
+pub struct DebugOverlay {
+    enabled: bool,
+    selected: Option<u16>,
+    // off while recording or playing back, a frozen bat isn't in the replay so it would go out of sync
+    can_freeze: bool,
+    info_text: Text,
+    center_texture: Texture,
+}
+
+impl DebugOverlay {
+    pub fn new(font: Font, center_texture: Texture, can_freeze: bool) -> DebugOverlay {
+        DebugOverlay {
+            enabled: false,
+            selected: None,
+            can_freeze,
+            info_text: Text::new("", font),
+            center_texture,
+        }
+    }
+
+    pub fn update(&mut self, ctx: &Context, simulation: &mut Simulation) {
+        if input::is_key_pressed(ctx, Key::F1) {
+            self.enabled = !self.enabled;
+        }
+        if !self.enabled || !input::is_mouse_button_pressed(ctx, MouseButton::Left) {
+            return;
+        }
+
+        let mouse = simulation.camera().screen_to_world(input::get_mouse_position(ctx));
+        let clicked = simulation
+            .enemies()
+            .iter()
+            .map(|enemy| (enemy.get_id(), enemy.get_pos().distance(mouse)))
+            .filter(|&(_, distance)| distance <= PICK_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(id, _)| id);
+
+        let Some(id) = clicked else {
+            return;
+        };
+        if self.can_freeze {
+            if let Some(previous) = self.selected {
+                simulation.set_bat_frozen(previous, false);
+            }
+        }
+        if self.selected == Some(id) {
+            self.selected = None;
+        } else {
+            if self.can_freeze {
+                simulation.set_bat_frozen(id, true);
+            }
+            self.selected = Some(id);
+        }
+    }
+
+    // the world it was pointing into is gone, and none of the new bats are frozen
+    pub fn clear_selection(&mut self) {
+        self.selected = None;
+    }
+
+    fn arrow(builder: &mut GeometryBuilder, color: Color, from: Vec2<f32>, vector: Vec2<f32>) -> tetra::Result {
+        let length = vector.magnitude();
+        if length < 1.0 {
+            return Ok(());
+        }
+        let to = from + vector;
+        let back = -vector / length * 5.0;
+        let side = Vec2::new(-back.y, back.x) * 0.5;
+        builder.set_color(color);
+        builder.polyline(1.0, &[from, to])?;
+        builder.polyline(1.0, &[to + back + side, to, to + back - side])?;
+        Ok(())
+    }
+
+    fn add_bat(&self, builder: &mut GeometryBuilder, enemy: &Enemy, visual_range: f32) -> tetra::Result {
+        let pos = enemy.get_pos();
+        let forces = enemy.get_forces();
+
+        builder.set_color(RANGE_COLOR);
+        builder.circle(ShapeStyle::Stroke(1.0), pos, visual_range)?;
+        if self.selected == Some(enemy.get_id()) {
+            builder.set_color(SELECTED_COLOR);
+            builder.circle(ShapeStyle::Stroke(2.0), pos, PICK_RADIUS)?;
+        }
+
+        DebugOverlay::arrow(builder, VELOCITY_COLOR, pos, enemy.get_vel() * VELOCITY_SCALE)?;
+        DebugOverlay::arrow(builder, COHESION_COLOR, pos, forces.cohesion * FORCE_SCALE)?;
+        DebugOverlay::arrow(builder, SEPARATION_COLOR, pos, forces.separation * FORCE_SCALE)?;
+        DebugOverlay::arrow(builder, ALIGNMENT_COLOR, pos, forces.alignment * FORCE_SCALE)?;
+        DebugOverlay::arrow(builder, PLAYER_COLOR, pos, forces.player * FORCE_SCALE)?;
+        DebugOverlay::arrow(builder, OBSTACLE_COLOR, pos, forces.obstacle * FORCE_SCALE)?;
+        Ok(())
+    }
+
+    // the shapes, drawn in world coordinates along with everything else
+    pub fn draw_world(&mut self, ctx: &mut Context, simulation: &Simulation) -> tetra::Result {
+        if !self.enabled || simulation.enemies().is_empty() {
+            return Ok(());
+        }
+
+        let visual_range = simulation.flock_config().visual_range;
+        let mut builder = GeometryBuilder::new();
+        for enemy in simulation.enemies() {
+            self.add_bat(&mut builder, enemy, visual_range)?;
+        }
+        builder.build_mesh(ctx)?.draw(ctx, Vec2::new(0.0, 0.0));
+
+        // perceived centre of each bat's neighbours
+        for enemy in simulation.enemies() {
+            self.center_texture.draw(ctx, DrawParams::new()
+                .position(enemy.get_center())
+                .origin(Vec2::new(8.0, 8.0))
+                .color(Color::rgba(1.0, 1.0, 1.0, 0.5)));
+        }
+        Ok(())
+    }
+
+    // the selected bat's numbers, drawn over the window
+    pub fn draw(&mut self, ctx: &mut Context, simulation: &Simulation) {
+        if !self.enabled {
+            return;
+        }
+        let selected = self
+            .selected
+            .and_then(|id| simulation.enemies().iter().find(|enemy| enemy.get_id() == id));
+        if let Some(enemy) = selected {
+            let forces = enemy.get_forces();
+            self.info_text.set_content(format!(
+                "bat {}{}\npos {:7.1} {:7.1}\nvel {:7.1} {:7.1}\nseen {}\ncoh {:7.1} {:7.1}\nsep {:7.1} {:7.1}\nali {:7.1} {:7.1}\nply {:7.1} {:7.1}\nobs {:7.1} {:7.1}",
+                enemy.get_id(),
+                if enemy.is_frozen() { " (frozen)" } else { "" },
+                enemy.get_pos().x, enemy.get_pos().y,
+                enemy.get_vel().x, enemy.get_vel().y,
+                enemy.get_boids_seen(),
+                forces.cohesion.x, forces.cohesion.y,
+                forces.separation.x, forces.separation.y,
+                forces.alignment.x, forces.alignment.y,
+                forces.player.x, forces.player.y,
+                forces.obstacle.x, forces.obstacle.y,
+            ));
+            self.info_text.draw(ctx, DrawParams::new()
+                .position(Vec2::new(8.0, 40.0))
+                .color(Color::BLACK));
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::config;
+use super::{DOWN_SPEED_INITIAL, ENEMY_NUM};
+
+// one step of the difficulty curve. a level starts once the run has lasted `time` seconds or the
+// player has scored `score`, whichever comes first. a level with neither starts straight away
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Level {
+    #[serde(default)]
+    pub time: Option<f32>,
+    #[serde(default)]
+    pub score: Option<u32>,
+    // pixels per second
+    pub down_speed: f32,
+    pub bats: usize,
+    // multiply the flock config's max_speed and player_attraction
+    #[serde(default = "one")]
+    pub bat_speed: f32,
+    #[serde(default = "one")]
+    pub attraction: f32,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+impl Level {
+    fn reached(&self, elapsed: f32, score: u32) -> bool {
+        match (self.time, self.score) {
+            (None, None) => true,
+            (time, needed) => time.is_some_and(|time| elapsed >= time) || needed.is_some_and(|needed| score >= needed),
+        }
+    }
+}
+
+// the whole curve, easiest first. read from a toml file so it can be reshaped without recompiling
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Difficulty {
+    #[serde(rename = "level")]
+    levels: Vec<Level>,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        let level = |time, score, down_speed, bats, bat_speed, attraction| Level {
+            time: Some(time),
+            score: Some(score),
+            down_speed,
+            bats,
+            bat_speed,
+            attraction,
+        };
+        Difficulty {
+            levels: vec![
+                Level { time: None, score: None, down_speed: DOWN_SPEED_INITIAL, bats: ENEMY_NUM, bat_speed: 1.0, attraction: 1.0 },
+                level(30.0, 10, 30.0, 50, 1.1, 1.1),
+                level(60.0, 20, 38.0, 60, 1.2, 1.25),
+                level(90.0, 35, 46.0, 70, 1.3, 1.4),
+                level(120.0, 50, 56.0, 85, 1.45, 1.6),
+                level(180.0, 75, 68.0, 100, 1.6, 1.8),
+            ],
+        }
+    }
+}
+
+impl Difficulty {
+    // a single level that never changes, for when the curve would get in the way
+    pub fn constant(bats: usize) -> Difficulty {
+        Difficulty {
+            levels: vec![Level {
+                time: None,
+                score: None,
+                down_speed: DOWN_SPEED_INITIAL,
+                bats,
+                bat_speed: 1.0,
+                attraction: 1.0,
+            }],
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> tetra::Result<Difficulty> {
+        let path = path.as_ref();
+        let difficulty: Difficulty = config::load_toml(path, "difficulty table")?;
+        difficulty.validate().map_err(|e| config::invalid(path, "difficulty table", e))?;
+        Ok(difficulty)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.levels.is_empty() {
+            return Err("needs at least one [[level]]".to_string());
+        }
+        for (i, level) in self.levels.iter().enumerate() {
+            let numbers = [level.down_speed, level.bat_speed, level.attraction, level.time.unwrap_or(0.0)];
+            if numbers.iter().any(|n| !n.is_finite() || *n < 0.0) {
+                return Err(format!("level {} has a negative or non-finite value", i + 1));
+            }
+            if level.bats > u16::MAX as usize {
+                return Err(format!("level {} has more than {} bats", i + 1, u16::MAX));
+            }
+        }
+        Ok(())
+    }
+
+    // the hardest level reached so far. levels are only ever climbed, never dropped back down
+    pub fn level_for(&self, current: usize, elapsed: f32, score: u32) -> usize {
+        let mut level = current;
+        while level + 1 < self.levels.len() && self.levels[level + 1].reached(elapsed, score) {
+            level += 1;
+        }
+        level
+    }
+
+    pub fn level(&self, index: usize) -> &Level {
+        &self.levels[index.min(self.levels.len() - 1)]
+    }
+}
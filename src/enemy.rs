@@ -1,11 +1,28 @@
 
-use tetra::graphics::{Texture, DrawParams};
 use tetra::math::Vec2;
 use tetra::Context;
-use rand::rngs::ThreadRng;
-use rand::{self, Rng};
-use super::POSITION_BUFFER_SIZE;
-use super::queue::Queue;
+use rand::Rng;
+
+use super::animation::{Animation, Frame};
+use super::collision::Aabb;
+use super::flock_config::FlockConfig;
+use super::game_object::{GameObject, ObjectKind, Textures, World};
+use super::simulation::{GameEvent, GameRng};
+
+// number of points checked along the look ahead when avoiding platforms
+const AVOID_SAMPLES: usize = 4;
+// indices into bat-sheet.png: wings up, level, down and back through level
+static FLAP: [Frame; 4] = [
+    Frame { index: 0, duration: 0.08 },
+    Frame { index: 1, duration: 0.08 },
+    Frame { index: 2, duration: 0.08 },
+    Frame { index: 1, duration: 0.08 },
+];
+// flapping runs at this multiple of FLAP's speed when hovering and when flat out
+const SLOWEST_FLAP: f32 = 0.5;
+const FASTEST_FLAP: f32 = 2.0;
+// seconds of flap each bat id is ahead of the last, so a fresh swarm doesn't flap in unison
+const FLAP_STAGGER: f32 = 0.037;
 
 #[derive(Debug, Clone)]
 
@@ -13,36 +30,74 @@ enum BatState {
     Debug,
     Boid,
 }
+// a snapshot of the whole flock taken at the start of a tick, shared by every bat's update
+pub struct FlockView<'a> {
+    pub ids: &'a [u16],
+    pub positions: &'a [Vec2<f32>],
+    pub velocities: &'a [Vec2<f32>],
+}
+
+// the individual steering terms from the last update, kept around for the debug overlay
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BoidForces {
+    pub cohesion: Vec2<f32>,
+    pub separation: Vec2<f32>,
+    pub alignment: Vec2<f32>,
+    pub player: Vec2<f32>,
+    pub window: Vec2<f32>,
+    pub obstacle: Vec2<f32>,
+}
+
+impl BoidForces {
+    pub fn total(&self) -> Vec2<f32> {
+        self.cohesion + self.separation + self.alignment + self.player + self.window + self.obstacle
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Enemy {
     id: u16,
-    textures: Vec<Texture>,
     position: Vec2<f32>,
+    prev_position: Vec2<f32>,
     velocity: Vec2<f32>,
     dimension: Vec2<f32>,
     is_facing_left: bool,
     state: BatState,
     debug: bool,
     boids_center: Vec2<f32>,
-    visual_range: f32
+    boids_seen: usize,
+    forces: BoidForces,
+    animation: Animation,
+    // scratch space for the spatial hash query, kept so it isn't reallocated every tick
+    neighbours: Vec<usize>,
 }
 
 impl Enemy {
-    pub fn new(id: u16, ts: Vec<Texture>, dim: Vec2<f32>, rng: &mut ThreadRng, visual_range: f32, debug: bool) -> Enemy {
-        let x_pos = rng.gen::<f32>() * 640.0;
-        let y_pos = rng.gen::<f32>() * 480.0 - 200.0;
+    pub fn new(id: u16, dim: Vec2<f32>, rng: &mut GameRng, debug: bool) -> Enemy {
+        Enemy::spawn_in(id, dim, &Aabb::new(Vec2::new(0.0, -200.0), Vec2::new(640.0, 480.0)), rng, debug)
+    }
+
+    // somewhere random inside area
+    pub fn spawn_in(id: u16, dim: Vec2<f32>, area: &Aabb, rng: &mut GameRng, debug: bool) -> Enemy {
+        let x_pos = area.min().x + rng.gen::<f32>() * area.size.x;
+        let y_pos = area.min().y + rng.gen::<f32>() * area.size.y;
+        let mut animation = Animation::new(&FLAP, true);
+        animation.update(id as f32 * FLAP_STAGGER);
 
         Enemy {     
             id,
-            textures: ts,
             position: Vec2::new(x_pos,y_pos),
+            prev_position: Vec2::new(x_pos,y_pos),
             velocity: Vec2::new(0.0,0.0),
             dimension: dim,
             is_facing_left: true,
             state: BatState::Boid,
             debug,
             boids_center: Vec2::new(0.0,0.0),
-            visual_range,
+            boids_seen: 0,
+            forces: BoidForces::default(),
+            animation,
+            neighbours: Vec::new(),
         }
     }
 
@@ -50,17 +105,35 @@ impl Enemy {
         self.position
     }
 
-    pub fn get_dim(&self) -> Vec2<f32> {
-        self.dimension
+    pub fn get_id(&self) -> u16 {
+        self.id
     }
 
     pub fn get_vel(&self) -> Vec2<f32> {
         self.velocity
     }
 
+    pub fn get_center(&self) -> Vec2<f32> {
+        self.boids_center
+    }
+
+    pub fn get_boids_seen(&self) -> usize {
+        self.boids_seen
+    }
+
+    pub fn get_forces(&self) -> BoidForces {
+        self.forces
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        matches!(self.state, BatState::Debug)
+    }
+
+    // a frozen bat stands still, so it doesn't drag its neighbours along through alignment
     pub fn set_state(&mut self, debug: bool) {
         if debug {
             self.state = BatState::Debug;
+            self.velocity = Vec2::zero();
         } else {
             self.state = BatState::Boid;
         }
@@ -68,40 +141,37 @@ impl Enemy {
 
     
 
-    fn rule1(&self) -> Vec2<f32> {
-        (self.boids_center - self.position) * 0.005
+    fn rule1(&self, config: &FlockConfig) -> Vec2<f32> {
+        (self.boids_center - self.position) * config.cohesion
     }
 
     fn distance(&self, b: &Vec2<f32>) -> f32 {
         ((self.position.x - b.x).powi(2) + (self.position.y - b.y).powi(2)).sqrt()
     }
 
-    fn rule2(&self, boids_pos: &Vec<Vec2<f32>>) -> Vec2<f32> {
-        let distance: f32 = 15.0;
-        let avoid = 0.005;
+    fn rule2(&self, flock: &FlockView, boids_seen: &[usize], config: &FlockConfig) -> Vec2<f32> {
         let mut result:Vec2<f32> = Vec2::new(0.0,0.0);
-        for b in boids_pos {
+        for b in boids_seen.iter().map(|&i| &flock.positions[i]) {
             let distance_from = self.distance(b);
-            if distance_from < distance { // && distance_from != 0.0
+            if distance_from < config.separation_distance {
                 result += self.position - b;
             }
         }
-        result * avoid
+        result * config.separation
     }
 
-    fn rule3(&self, boids_vel: &Vec<Vec2<f32>>) -> Vec2<f32> {
+    fn rule3(&self, flock: &FlockView, boids_seen: &[usize], config: &FlockConfig) -> Vec2<f32> {
+        if boids_seen.is_empty() {
+            return Vec2::new(0.0,0.0);
+        }
         let mut result:Vec2<f32> = Vec2::new(0.0,0.0);
-        let factor = 0.005;
-        for b in boids_vel {
-            if *b != self.velocity {
-                result += *b;
-            }
+        for b in boids_seen.iter().map(|&i| flock.velocities[i]) {
+            result += b;
         }
-        ((result / ((boids_vel.len() - 1) as f32)) - self.velocity) * factor
+        ((result / (boids_seen.len() as f32)) - self.velocity) * config.alignment
     }
 
-    fn limit_speed(&mut self) {
-        let max_speed = 4.5;
+    fn limit_speed(&mut self, max_speed: f32) {
         let magnitude = (self.velocity.x * self.velocity.x + self.velocity.y * self.velocity.y).sqrt();
         if magnitude > max_speed {
             self.velocity.x = (self.velocity.x / magnitude) * max_speed;
@@ -109,120 +179,175 @@ impl Enemy {
         }
     }
 
-    fn stay_within_window(&self) -> Vec2<f32> {
+    // the view moves, so the swarm is kept on screen rather than in one place
+    fn stay_within_view(&self, view: &Aabb, config: &FlockConfig) -> Vec2<f32> {
         let mut result:Vec2<f32> = Vec2::new(0.0,0.0);
-        let margin = 10.0;
-        let turn = 0.2;
+        let margin = config.window_margin;
+        let turn = config.window_turn;
+        let (min, max) = (view.min(), view.max());
 
-        if self.position.x < margin {
+        if self.position.x < min.x + margin {
             result.x += turn;
         } 
-        if self.position.x > crate::WINDOW_WIDTH as f32 - margin {
+        if self.position.x > max.x - margin {
             result.x -= turn;
         }
-        if self.position.y < margin {
+        if self.position.y < min.y + margin {
             result.y += turn;
         }
-        if self.position.y > crate::WINDOW_HEIGHT as f32 - margin {
+        if self.position.y > max.y - margin {
             result.y -= turn;
         }
         result
     }
 
-    fn boids_towards_player(&self, player_pos: Vec2<f32>) -> Vec2<f32> {
-        let factor = 0.0065;
+    fn boids_towards_player(&self, player_pos: Vec2<f32>, config: &FlockConfig) -> Vec2<f32> {
         let distance_to_player = self.distance(&player_pos);
-        if distance_to_player < self.visual_range {
-            (player_pos - self.position) * factor
+        if distance_to_player < config.visual_range {
+            (player_pos - self.position) * config.player_attraction
         } else {
             Vec2::new(0.0,0.0)
         }
     }
 
-    fn boids_in_range(&self, boids_pos: &Vec<Vec2<f32>>) -> Vec<Vec2<f32>> {
-        boids_pos
-            .iter() // Iterate over references to the positions
-            .filter(|&pos| self.distance(pos) <= self.visual_range) // Filter based on distance
-            .cloned() // Clone the Vec2 to return owned data
+    // steer out of the first platform found along the look ahead, harder the sooner it's hit
+    fn avoid_platforms(&self, platforms: &[Aabb], config: &FlockConfig) -> Vec2<f32> {
+        let speed = self.velocity.magnitude();
+        if speed == 0.0 {
+            return Vec2::new(0.0,0.0);
+        }
+        let heading = self.velocity / speed;
+
+        for step in 0..=AVOID_SAMPLES {
+            let t = step as f32 / AVOID_SAMPLES as f32;
+            let ahead = self.position + heading * config.avoid_lookahead * t;
+            for platform in platforms {
+                let obstacle = platform.expanded(config.avoid_margin);
+                if !obstacle.contains_point(ahead) {
+                    continue;
+                }
+                let (min, max) = (obstacle.min(), obstacle.max());
+
+                // leave through whichever edge the look ahead point is closest to
+                let edges = [
+                    (ahead.x - min.x, Vec2::new(-1.0, 0.0)),
+                    (max.x - ahead.x, Vec2::new(1.0, 0.0)),
+                    (ahead.y - min.y, Vec2::new(0.0, -1.0)),
+                    (max.y - ahead.y, Vec2::new(0.0, 1.0)),
+                ];
+                let (_, normal) = edges
+                    .into_iter()
+                    .min_by(|a, b| a.0.total_cmp(&b.0))
+                    .unwrap_or(edges[0]);
+                return normal * config.avoidance * (1.0 - t * 0.5);
+            }
+        }
+        Vec2::new(0.0,0.0)
+    }
+
+    // indices of every other bat this one can see, which is all any of the three rules look at
+    fn boids_in_range(&self, flock: &FlockView, neighbours: &[usize], visual_range: f32) -> Vec<usize> {
+        neighbours
+            .iter()
+            .copied() // only the boids in nearby grid cells
+            .filter(|&i| flock.ids[i] != self.id) // leave ourselves out by id, not by value
+            .filter(|&i| self.distance(&flock.positions[i]) <= visual_range)
             .collect()
     }
 
-    fn calculate_center(&mut self, boids_seen: &Vec<Vec2<f32>>) {
-        let mut pos_sum = Vec2::<f32>::new(0.02,0.0);
-            for b in boids_seen {
-                pos_sum += *b;
-            }
+    fn calculate_center(&mut self, flock: &FlockView, boids_seen: &[usize]) {
+        if boids_seen.is_empty() {
+            // nothing in sight, so there's nothing to steer towards
+            self.boids_center = self.position;
+            return;
+        }
+        let mut pos_sum = Vec2::<f32>::new(0.0,0.0);
+        for b in boids_seen.iter().map(|&i| flock.positions[i]) {
+            pos_sum += b;
+        }
         self.boids_center = pos_sum / (boids_seen.len() as f32);
-        // if self.boids_center.x == 0.0 && self.boids_center.y == 0.0 {
-        //     self.boids_center = Vec2::<f32>::new(crate::WINDOW_WIDTH as f32 / 2.0,crate::WINDOW_HEIGHT as f32 / 2.0);
-        // }
     }
 
-    fn boid(&mut self, player_pos_buf: Vec2<f32>, boids_pos: &Vec<Vec2<f32>>, boids_vel: &Vec<Vec2<f32>>) -> Vec2<f32> {
-        let boids_seen = self.boids_in_range(boids_pos);
-        self.calculate_center(&boids_seen);
-        let mut new_vel: Vec2<f32> = Vec2 { x: (0.0), y: (0.0) };
+    fn boid(&mut self, player_pos_buf: Vec2<f32>, flock: &FlockView, neighbours: &[usize], platforms: &[Aabb], view: &Aabb, config: &FlockConfig) -> Vec2<f32> {
+        let boids_seen = self.boids_in_range(flock, neighbours, config.visual_range);
+        self.boids_seen = boids_seen.len();
+        self.calculate_center(flock, &boids_seen);
 
-        let towards_player = self.boids_towards_player(player_pos_buf);
-        let rule1 = self.rule1(); // towards boids center
-        let rule2 = self.rule2(boids_pos); // avoid others
-        let rule3 = self.rule3(boids_vel); // match speed
-        let stay_inside = self.stay_within_window();
+        self.forces = BoidForces {
+            cohesion: self.rule1(config), // towards boids center
+            separation: self.rule2(flock, &boids_seen, config), // avoid others
+            alignment: self.rule3(flock, &boids_seen, config), // match speed
+            player: self.boids_towards_player(player_pos_buf, config),
+            window: self.stay_within_view(view, config),
+            obstacle: self.avoid_platforms(platforms, config),
+        };
+        self.forces.total()
+    }
 
-        // if new_vel.x == 0.0 && new_vel.y == 0.0 {
-        //     new_vel.x = 0.05;
-        //     new_vel.y = 0.05;
-        // }
+}
 
-        new_vel += 1.0 * (rule1 + rule2 + rule3 + stay_inside + towards_player);
-        new_vel
+impl GameObject for Enemy {
+    fn kind(&self) -> ObjectKind {
+        ObjectKind::Enemy
     }
 
-    pub fn toggle_state(&mut self) {
-        match self.state {
-            BatState::Debug => {self.state = BatState::Boid;
-            self.velocity.x *= 10.74; self.velocity.y *= 10.5;},
-            BatState::Boid => self.state = BatState::Debug,
-        }
+    fn bounds(&self) -> Aabb {
+        Aabb::new(self.position, self.dimension)
     }
 
-    pub fn update(&mut self, player_pos: Vec2<f32>, boids_pos: &Vec<Vec2<f32>>, boids_vel: &Vec<Vec2<f32>>) {
+    fn update(&mut self, world: &World, _rng: &mut GameRng, _events: &mut Vec<GameEvent>) {
+        let config = world.flock_config;
+        self.prev_position = self.position;
+        // neighbours are indices into the flock view
+        let mut neighbours = std::mem::take(&mut self.neighbours);
+        world.flock_grid.query(self.position, config.visual_range, &mut neighbours);
+        let steering = self.boid(world.player_trail, world.flock, &neighbours, world.platforms, &world.view, config);
+        self.neighbours = neighbours;
         match self.state {
-            BatState::Debug => {self.velocity = Vec2::new(0.0,0.0)},
-            BatState::Boid => {self.velocity = self.velocity * 1.01 + self.boid(player_pos, boids_pos, boids_vel)}
-        }
-        self.limit_speed();
-        // self.boids_center = boids_center;
-        if self.velocity.x < 0.0 {
-            self.is_facing_left = true;
-        } else {
-            self.is_facing_left = false;
+            // frozen in place for inspection, the forces above still get refreshed
+            BatState::Debug => return,
+            BatState::Boid => {
+                self.velocity = self.velocity * (config.velocity_growth * world.dt).exp() + steering * world.dt;
+            }
         }
-        self.position += self.velocity;
+        self.limit_speed(config.max_speed);
+        self.is_facing_left = self.velocity.x < 0.0;
+        let effort = (self.velocity.magnitude() / config.max_speed).min(1.0);
+        self.animation.update(world.dt * (SLOWEST_FLAP + (FASTEST_FLAP - SLOWEST_FLAP) * effort));
+        self.position += self.velocity * world.dt;
         if self.debug {
             // println!("Pos: {},{} | Vel: {},{}", self.position.x, self.position.y, self.velocity.x, self.velocity.y);
         }
     }
 
-    pub fn draw(&self, ctx: &mut Context) {
-        if self.is_facing_left {
-            self.textures[0].draw(ctx, DrawParams::new()
-            .position(self.position)
-            .origin(Vec2::new(8.0, 8.0))
-            .scale(Vec2::new(2.0, 2.0)),);
-        } else {
-            self.textures[1].draw(ctx, DrawParams::new()
-            .position(self.position)
-            .origin(Vec2::new(8.0, 8.0))
-            .scale(Vec2::new(2.0, 2.0)),);
-        }
+    fn draw(&self, ctx: &mut Context, textures: &Textures, blend: f32) {
+        let position = Vec2::lerp(self.prev_position, self.position, blend);
+        textures.bat.draw(ctx, self.animation.index(), position, 2.0, self.is_facing_left);
+    }
+}
 
-        // draws percieved center for debugging
-        // if self.debug {
-        //     self.textures[2].draw(ctx, DrawParams::new()
-        //     .position(self.boids_center)
-        //     .origin(Vec2::new(8.0, 8.0))
-        //     .scale(Vec2::new(2.0, 2.0)),);
-        // }
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use tetra::math::Vec2;
+
+    use super::Enemy;
+    use crate::simulation::GameRng;
+
+    #[test]
+    fn freezing_stops_the_bat() {
+        let mut rng = GameRng::seed_from_u64(1);
+        let mut enemy = Enemy::new(0, Vec2::new(20.0, 20.0), &mut rng, false);
+        enemy.velocity = Vec2::new(50.0, -30.0);
+
+        enemy.set_state(true);
+        assert!(enemy.is_frozen());
+        assert_eq!(enemy.get_vel(), Vec2::zero());
+
+        enemy.set_state(false);
+        assert!(!enemy.is_frozen());
+        enemy.velocity = Vec2::new(10.0, 0.0);
+        enemy.set_state(true);
+        assert_eq!(enemy.get_vel(), Vec2::zero());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,58 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::config;
+use super::BAT_VISUAL_RANGE;
+
+// tuning knobs for the bat swarm, read from a toml file so they can change without recompiling.
+// any field missing from the file keeps the value below. steering terms are accelerations
+// (pixels per second squared per pixel of offset, or per pixel per second of velocity difference)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FlockConfig {
+    pub visual_range: f32,
+    // rule1, steer towards the centre of the visible boids
+    pub cohesion: f32,
+    // rule2, steer away from boids closer than separation_distance
+    pub separation_distance: f32,
+    pub separation: f32,
+    // rule3, match the velocity of the other boids
+    pub alignment: f32,
+    pub max_speed: f32,
+    pub window_margin: f32,
+    pub window_turn: f32,
+    pub player_attraction: f32,
+    // bats speed up by a factor of e every 1 / velocity_growth seconds until max_speed
+    pub velocity_growth: f32,
+    // how far ahead along its velocity a bat looks for platforms, and how hard it turns away
+    pub avoid_lookahead: f32,
+    pub avoid_margin: f32,
+    pub avoidance: f32,
+}
+
+impl Default for FlockConfig {
+    fn default() -> Self {
+        FlockConfig {
+            visual_range: BAT_VISUAL_RANGE,
+            cohesion: 18.0,
+            separation_distance: 15.0,
+            separation: 18.0,
+            alignment: 0.3,
+            max_speed: 270.0,
+            window_margin: 10.0,
+            window_turn: 720.0,
+            player_attraction: 23.4,
+            velocity_growth: 0.6,
+            avoid_lookahead: 40.0,
+            avoid_margin: 10.0,
+            avoidance: 2160.0,
+        }
+    }
+}
+
+impl FlockConfig {
+    pub fn load<P: AsRef<Path>>(path: P) -> tetra::Result<FlockConfig> {
+        config::load_toml(path.as_ref(), "flock config")
+    }
+}
@@ -1,25 +1,56 @@
 use tetra::graphics::Texture;
 use tetra::math::Vec2;
+use tetra::Context;
 
-struct GameObject {
-    position: Vec2<f32>,
-    dimension: Vec2<f32>,
-    velocity: Vec2<f32>,
-    textures: Vec<Texture>,
+use super::animation::SpriteSheet;
+use super::collision::Aabb;
+use super::enemy::FlockView;
+use super::flock_config::FlockConfig;
+use super::simulation::{GameEvent, GameRng, InputFrame};
+use super::spatial_hash::SpatialHash;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Player,
+    Platform,
+    Token,
+    Enemy,
+}
+
+// every texture an object might draw with, loaded once up front
+pub struct Textures {
+    pub player: SpriteSheet,
+    pub platform: Texture,
+    pub brick: Texture,
+    pub token: Texture,
+    pub bat: SpriteSheet,
+}
+
+// what an object can see while it updates. it's all taken at the start of the tick,
+// so the order objects update in doesn't matter
+pub struct World<'a> {
+    pub dt: f32,
+    // the part of the world on screen
+    pub view: Aabb,
+    pub inputs: &'a InputFrame,
+    // where the bats think the player is
+    pub player_trail: Vec2<f32>,
+    pub platforms: &'a [Aabb],
+    pub tokens: &'a [Aabb],
+    pub enemies: &'a [Aabb],
+    pub flock: &'a FlockView<'a>,
+    pub flock_grid: &'a SpatialHash,
+    pub flock_config: &'a FlockConfig,
 }
 
-impl GameObject {
-    fn new(txts: Vec<Texture>, pos: Vec2<f32>, vel: Vec2<f32>, dim: Vec2<f32>) -> GameObject {
-        GameObject {        
-            position: pos,
-            dimension: dim,
-            velocity: vel,      
-            textures: txts,
-        }
-    }
-
-    fn update(&mut self) {
-        self.position.y += self.velocity.y;
-        self.position.x += self.velocity.x;
-    }
-}
\ No newline at end of file
+pub trait GameObject {
+    fn kind(&self) -> ObjectKind;
+
+    fn bounds(&self) -> Aabb;
+
+    // anything the rest of the game needs to react to goes into events
+    fn update(&mut self, world: &World, rng: &mut GameRng, events: &mut Vec<GameEvent>);
+
+    // blend is how far we are between the last tick and the next one
+    fn draw(&self, ctx: &mut Context, textures: &Textures, blend: f32);
+}
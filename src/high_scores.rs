@@ -0,0 +1,231 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+const MAX_ENTRIES: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HighScore {
+    pub score: u32,
+    // seconds
+    pub survival_time: f32,
+    pub seed: u64,
+    // seconds since the unix epoch
+    pub date: u64,
+}
+
+impl HighScore {
+    // a run that just ended
+    pub fn now(score: u32, survival_time: f32, seed: u64) -> HighScore {
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        HighScore { score, survival_time, seed, date }
+    }
+
+    // the date as yyyy-mm-dd in utc
+    pub fn date_string(&self) -> String {
+        // days since the epoch to a civil date, from Howard Hinnant's date algorithms
+        let days = (self.date / 86_400) as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+// the best runs so far, best first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HighScores {
+    #[serde(default, rename = "entry")]
+    entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    // where the table lives unless told otherwise, None if the platform has no data directory
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("tetras").join("high_scores.toml"))
+    }
+
+    // never fails: a missing file is an empty table, and a broken one is moved aside so the
+    // next save doesn't silently throw it away
+    pub fn load<P: AsRef<Path>>(path: P) -> HighScores {
+        let path = path.as_ref();
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return HighScores::default(),
+            Err(e) => {
+                eprintln!("couldn't read high scores {}: {}", path.display(), e);
+                return HighScores::default();
+            }
+        };
+
+        match toml::from_str::<HighScores>(&text) {
+            Ok(mut table) => {
+                // it's a text file, so don't trust it to still be sorted or short enough
+                table.entries.retain(|entry| entry.survival_time.is_finite());
+                table.sort();
+                table
+            }
+            Err(e) => {
+                let backup = path.with_extension("toml.bad");
+                eprintln!(
+                    "high scores {} are corrupt, starting a new table and keeping the old one as {}: {}",
+                    path.display(),
+                    backup.display(),
+                    e
+                );
+                if let Err(e) = fs::rename(path, &backup) {
+                    eprintln!("couldn't move {} aside: {}", path.display(), e);
+                }
+                HighScores::default()
+            }
+        }
+    }
+
+    // written next to the real file and renamed over it, so a crash mid-save can't corrupt the table
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let temp = path.with_extension("toml.tmp");
+        fs::write(&temp, text)?;
+        fs::rename(&temp, path)
+    }
+
+    // the entry's place in the table counting from 0, or None if it didn't make it
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        let rank = self
+            .entries
+            .iter()
+            .position(|other| HighScores::beats(&entry, other))
+            .unwrap_or(self.entries.len());
+        if rank >= MAX_ENTRIES {
+            return None;
+        }
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+
+    pub fn entries(&self) -> &[HighScore] {
+        &self.entries
+    }
+
+    // higher score first, surviving longer breaks ties. a new entry doesn't beat an equal old one
+    fn beats(a: &HighScore, b: &HighScore) -> bool {
+        a.score > b.score || (a.score == b.score && a.survival_time > b.survival_time)
+    }
+
+    fn sort(&mut self) {
+        self.entries.sort_by(|a, b| {
+            b.score.cmp(&a.score).then(b.survival_time.total_cmp(&a.survival_time))
+        });
+        self.entries.truncate(MAX_ENTRIES);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{HighScore, HighScores, MAX_ENTRIES};
+
+    // an empty directory of the test's own under the temp dir
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tetras-high-scores-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entry(score: u32, survival_time: f32) -> HighScore {
+        HighScore { score, survival_time, seed: 1, date: 0 }
+    }
+
+    fn full_table() -> HighScores {
+        let mut table = HighScores::default();
+        for score in 1..=MAX_ENTRIES as u32 {
+            table.insert(entry(score * 10, 5.0));
+        }
+        table
+    }
+
+    #[test]
+    fn missing_file_is_an_empty_table() {
+        let dir = temp_dir("missing");
+        assert!(HighScores::load(dir.join("high_scores.toml")).entries().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_file_is_moved_aside() {
+        let dir = temp_dir("corrupt");
+        let path = dir.join("high_scores.toml");
+        fs::write(&path, "[[entry]\nscore = ").unwrap();
+
+        assert!(HighScores::load(&path).entries().is_empty());
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(dir.join("high_scores.toml.bad")).unwrap(), "[[entry]\nscore = ");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    // hand edited tables get put back in order, cut down to size and lose entries that can't be ranked
+    #[test]
+    fn loaded_table_is_cleaned_up() {
+        let dir = temp_dir("unsorted");
+        let path = dir.join("high_scores.toml");
+        let mut text = String::new();
+        let entries = [
+            (5, "3.0"), (70, "1.0"), (20, "nan"), (90, "2.0"), (10, "4.0"), (30, "6.0"),
+            (70, "9.0"), (40, "1.0"), (60, "2.0"), (50, "3.0"), (80, "1.0"), (15, "2.0"),
+        ];
+        for (score, survival_time) in entries {
+            text.push_str(&format!("[[entry]]\nscore = {}\nsurvival_time = {}\nseed = 1\ndate = 0\n", score, survival_time));
+        }
+        fs::write(&path, text).unwrap();
+
+        let table = HighScores::load(&path);
+        let ranked: Vec<(u32, f32)> = table.entries().iter().map(|entry| (entry.score, entry.survival_time)).collect();
+        assert_eq!(
+            ranked,
+            [(90, 2.0), (80, 1.0), (70, 9.0), (70, 1.0), (60, 2.0), (50, 3.0), (40, 1.0), (30, 6.0), (15, 2.0), (10, 4.0)]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn saved_table_loads_back() {
+        let dir = temp_dir("round-trip");
+        let path = dir.join("nested").join("high_scores.toml");
+        let table = full_table();
+        table.save(&path).unwrap();
+        assert_eq!(HighScores::load(&path).entries(), table.entries());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn insert_ranks_entries() {
+        let mut table = full_table();
+        // below tenth place, and level with it since an equal score doesn't beat an old one
+        assert_eq!(table.insert(entry(5, 100.0)), None);
+        assert_eq!(table.insert(entry(10, 5.0)), None);
+        assert_eq!(table.entries().len(), MAX_ENTRIES);
+
+        assert_eq!(table.insert(entry(55, 1.0)), Some(5));
+        assert_eq!(table.entries().len(), MAX_ENTRIES);
+        assert_eq!(table.entries().last().unwrap().score, 20);
+    }
+}
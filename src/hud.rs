@@ -0,0 +1,97 @@
+use tetra::graphics::text::{Font, Text};
+use tetra::graphics::{Color, DrawParams, Texture};
+use tetra::math::Vec2;
+use tetra::{window, Context};
+
+use super::simulation::Simulation;
+
+// gap between the hud text and the edge of the window
+const MARGIN: f32 = 8.0;
+const TEXT_COLOR: Color = Color::BLACK;
+
+// which corner of the window a piece of the hud hangs off, so it stays put whatever the window size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Anchor {
+    // top left corner for something of this size to sit margin pixels in from the anchored corner
+    fn place(self, window_size: Vec2<f32>, size: Vec2<f32>, margin: Vec2<f32>) -> Vec2<f32> {
+        let x = match self {
+            Anchor::TopLeft | Anchor::BottomLeft => margin.x,
+            Anchor::TopRight | Anchor::BottomRight => window_size.x - size.x - margin.x,
+        };
+        let y = match self {
+            Anchor::TopLeft | Anchor::TopRight => margin.y,
+            Anchor::BottomLeft | Anchor::BottomRight => window_size.y - size.y - margin.y,
+        };
+        Vec2::new(x, y)
+    }
+}
+
+struct Label {
+    text: Text,
+    anchor: Anchor,
+}
+
+// everything drawn over the game while playing: health bar, score, level, time, distance and health
+pub struct Hud {
+    health_bar: Texture,
+    score: Label,
+    time: Label,
+    distance: Label,
+    health: Label,
+}
+
+impl Hud {
+    pub fn new(font: Font, health_bar: Texture) -> Hud {
+        let label = |anchor| Label { text: Text::new("", font.clone()), anchor };
+        Hud {
+            health_bar,
+            score: label(Anchor::TopLeft),
+            time: label(Anchor::TopRight),
+            distance: label(Anchor::BottomLeft),
+            health: label(Anchor::BottomRight),
+        }
+    }
+
+    pub fn update(&mut self, simulation: &Simulation) {
+        let player = simulation.player();
+        self.score.text.set_content(format!("SCORE {}", player.get_score()));
+        self.time.text.set_content(format!("LEVEL {}  TIME {:.1}s", simulation.level() + 1, simulation.elapsed()));
+        self.distance.text.set_content(format!("DEPTH {:.0}", simulation.distance()));
+        self.health.text.set_content(format!("HEALTH {:.0}", player.get_health().max(0.0).ceil()));
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context, simulation: &Simulation) {
+        let (width, height) = window::get_size(ctx);
+        let window_size = Vec2::new(width as f32, height as f32);
+
+        // the bar spans the top of the window and slides off to the left as health goes down
+        let health = simulation.player().get_health().clamp(0.0, 100.0);
+        let bar_scale = Vec2::new(window_size.x / self.health_bar.width() as f32, 1.0);
+        self.health_bar.draw(ctx, DrawParams::new()
+            .position(Vec2::new(-window_size.x * (100.0 - health) / 100.0, 0.0))
+            .scale(bar_scale));
+
+        // the text along the top has to clear the bar
+        let bar_height = self.health_bar.height() as f32;
+        for label in [&mut self.score, &mut self.time, &mut self.distance, &mut self.health] {
+            let size = label
+                .text
+                .get_bounds(ctx)
+                .map(|bounds| Vec2::new(bounds.width, bounds.height))
+                .unwrap_or_default();
+            let margin = match label.anchor {
+                Anchor::TopLeft | Anchor::TopRight => Vec2::new(MARGIN, MARGIN + bar_height),
+                Anchor::BottomLeft | Anchor::BottomRight => Vec2::new(MARGIN, MARGIN),
+            };
+            let position = label.anchor.place(window_size, size, margin);
+            label.text.draw(ctx, DrawParams::new().position(position).color(TEXT_COLOR));
+        }
+    }
+}
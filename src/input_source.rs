@@ -0,0 +1,154 @@
+use tetra::input::{self, GamepadAxis, GamepadButton};
+use tetra::Context;
+
+use super::controls::{Action, Bindings};
+use super::simulation::InputFrame;
+
+// stick travel that's ignored, worn sticks rest a little off centre
+pub const DEAD_ZONE: f32 = 0.2;
+// pushing the stick down further than this fast falls
+const FAST_FALL_STICK: f32 = 0.5;
+// tetra numbers pads from 0 as they're plugged in
+const MAX_GAMEPADS: usize = 4;
+
+// anything that can say what the player is doing this tick. sources that read hardware borrow
+// the Context when they're made, so the trait itself works headless
+pub trait InputSource {
+    fn next_frame(&mut self) -> InputFrame;
+}
+
+pub struct KeyboardInput<'a> {
+    ctx: &'a Context,
+    bindings: &'a Bindings,
+}
+
+impl<'a> KeyboardInput<'a> {
+    pub fn new(ctx: &'a Context, bindings: &'a Bindings) -> KeyboardInput<'a> {
+        KeyboardInput { ctx, bindings }
+    }
+}
+
+impl InputSource for KeyboardInput<'_> {
+    fn next_frame(&mut self) -> InputFrame {
+        let ctx = self.ctx;
+        let mut run = 0;
+        if self.bindings.is_down(ctx, Action::MoveLeft) {
+            run -= InputFrame::RUN_MAX;
+        }
+        if self.bindings.is_down(ctx, Action::MoveRight) {
+            run += InputFrame::RUN_MAX;
+        }
+        InputFrame {
+            run,
+            jump: self.bindings.is_pressed(ctx, Action::Jump),
+            fast_fall: self.bindings.is_down(ctx, Action::FastFall),
+            restart: self.bindings.is_pressed(ctx, Action::Restart),
+        }
+    }
+}
+
+// every connected pad at once: left stick or d-pad to run, A to jump, down to fast fall,
+// back to restart and start to pause
+pub struct GamepadInput<'a> {
+    ctx: &'a Context,
+    dead_zone: f32,
+}
+
+impl<'a> GamepadInput<'a> {
+    pub fn new(ctx: &'a Context, dead_zone: f32) -> GamepadInput<'a> {
+        GamepadInput { ctx, dead_zone }
+    }
+
+    // the stick with the dead zone cut out, rescaled so just past it is still a gentle push
+    fn stick(&self, value: f32) -> f32 {
+        if value.abs() <= self.dead_zone {
+            return 0.0;
+        }
+        value.signum() * (value.abs() - self.dead_zone) / (1.0 - self.dead_zone)
+    }
+
+    fn connected(ctx: &Context) -> impl Iterator<Item = usize> + '_ {
+        (0..MAX_GAMEPADS).filter(move |&id| input::is_gamepad_connected(ctx, id))
+    }
+
+    fn button(action: Action) -> Option<GamepadButton> {
+        match action {
+            Action::Jump => Some(GamepadButton::A),
+            Action::Restart => Some(GamepadButton::Back),
+            Action::Pause => Some(GamepadButton::Start),
+            Action::MoveLeft | Action::MoveRight | Action::FastFall => None,
+        }
+    }
+
+    // for the actions the game handles outside the simulation, like pausing
+    pub fn is_pressed(ctx: &Context, action: Action) -> bool {
+        match GamepadInput::button(action) {
+            Some(button) => GamepadInput::connected(ctx).any(|id| input::is_gamepad_button_pressed(ctx, id, button)),
+            None => false,
+        }
+    }
+}
+
+impl InputSource for GamepadInput<'_> {
+    fn next_frame(&mut self) -> InputFrame {
+        let ctx = self.ctx;
+        let mut frame = InputFrame::default();
+        for id in GamepadInput::connected(ctx) {
+            let mut run = self.stick(input::get_gamepad_axis_position(ctx, id, GamepadAxis::LeftStickX));
+            if input::is_gamepad_button_down(ctx, id, GamepadButton::Left) {
+                run = -1.0;
+            }
+            if input::is_gamepad_button_down(ctx, id, GamepadButton::Right) {
+                run = 1.0;
+            }
+            let down = input::get_gamepad_axis_position(ctx, id, GamepadAxis::LeftStickY) > FAST_FALL_STICK
+                || input::is_gamepad_button_down(ctx, id, GamepadButton::Down);
+
+            frame = frame.merge(InputFrame {
+                run: InputFrame::run_from(run),
+                jump: GamepadInput::is_pressed(ctx, Action::Jump),
+                fast_fall: down,
+                restart: GamepadInput::is_pressed(ctx, Action::Restart),
+            });
+        }
+        frame
+    }
+}
+
+// plays back a fixed list of frames, then nothing. for driving a headless Simulation
+pub struct ScriptedInput {
+    frames: Vec<InputFrame>,
+    next: usize,
+}
+
+impl ScriptedInput {
+    pub fn new(frames: Vec<InputFrame>) -> ScriptedInput {
+        ScriptedInput { frames, next: 0 }
+    }
+
+    // the same frame for a number of ticks, e.g. holding right for a second
+    pub fn hold(mut self, frame: InputFrame, ticks: usize) -> ScriptedInput {
+        self.frames.extend(std::iter::repeat_n(frame, ticks));
+        self
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.frames.len()
+    }
+}
+
+impl Iterator for ScriptedInput {
+    type Item = InputFrame;
+
+    fn next(&mut self) -> Option<InputFrame> {
+        let frame = self.frames.get(self.next).copied();
+        self.next += 1;
+        frame
+    }
+}
+
+impl InputSource for ScriptedInput {
+    fn next_frame(&mut self) -> InputFrame {
+        self.next().unwrap_or_default()
+    }
+}
@@ -0,0 +1,446 @@
+use std::fs;
+use std::path::Path;
+
+use roxmltree::Node;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tetra::math::Vec2;
+use tetra::TetraError;
+
+use super::collision::Aabb;
+use super::{WINDOW_HEIGHT, WINDOW_WIDTH};
+
+// a hand made layout read from a map saved by the Tiled editor (https://www.mapeditor.org), either
+// its own .tmx or exported as json.
+// tile layers named "platforms" and "bricks" become platforms, object layers named "player",
+// "tokens" and "bats" give the spawn point, token positions and areas the swarm starts in.
+// the bottom of the map lines up with the bottom of the window and the camera scrolls up it from there
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelMap {
+    pub platforms: Vec<Aabb>,
+    pub bricks: Vec<Aabb>,
+    pub player_spawn: Vec2<f32>,
+    pub tokens: Vec<Vec2<f32>>,
+    pub bat_zones: Vec<BatZone>,
+    // what the camera is kept inside, never less than a window
+    pub bounds: Aabb,
+}
+
+// bats start at random spots inside area, set count with an int custom property on the object
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BatZone {
+    pub area: Aabb,
+    pub count: usize,
+}
+
+// just the parts of the tiled format we use, anything else in the file is ignored. a tmx map is
+// read into these too, so both formats are checked the same way
+#[derive(Deserialize)]
+struct MapFile {
+    orientation: String,
+    #[serde(default)]
+    infinite: bool,
+    width: usize,
+    height: usize,
+    tilewidth: f32,
+    tileheight: f32,
+    layers: Vec<LayerFile>,
+}
+
+#[derive(Deserialize)]
+struct LayerFile {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    data: Option<Value>,
+    #[serde(default)]
+    encoding: Option<String>,
+    #[serde(default)]
+    objects: Vec<ObjectFile>,
+}
+
+#[derive(Deserialize)]
+struct ObjectFile {
+    #[serde(default)]
+    id: u32,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    properties: Vec<PropertyFile>,
+}
+
+#[derive(Deserialize)]
+struct PropertyFile {
+    name: String,
+    value: Value,
+}
+
+impl LevelMap {
+    pub fn load<P: AsRef<Path>>(path: P) -> tetra::Result<LevelMap> {
+        let path = path.as_ref();
+        let invalid = |message: String| TetraError::PlatformError(format!("invalid level {}: {}", path.display(), message));
+
+        let text = fs::read_to_string(path).map_err(|reason| TetraError::FailedToLoadAsset {
+            reason,
+            path: path.to_path_buf(),
+        })?;
+        let map = if path.extension().is_some_and(|extension| extension == "tmx") {
+            LevelMap::from_tmx(&text)
+        } else {
+            serde_json::from_str(&text).map_err(|e| e.to_string())
+        };
+        LevelMap::from_file(map.map_err(&invalid)?).map_err(invalid)
+    }
+
+    // the same fields the json has, pulled out of tiled's xml
+    fn from_tmx(text: &str) -> Result<MapFile, String> {
+        let document = roxmltree::Document::parse(text).map_err(|e| e.to_string())?;
+        let map = document.root_element();
+        if !map.has_tag_name("map") {
+            return Err(format!("expected a <map> but found <{}>", map.tag_name().name()));
+        }
+
+        let mut layers = Vec::new();
+        for node in map.children().filter(Node::is_element) {
+            let kind = match node.tag_name().name() {
+                "layer" => "tilelayer",
+                "objectgroup" => "objectgroup",
+                // tilesets, image layers and groups are just for the editor
+                _ => continue,
+            };
+            let mut layer = LayerFile {
+                name: node.attribute("name").unwrap_or_default().to_string(),
+                kind: kind.to_string(),
+                data: None,
+                encoding: None,
+                objects: Vec::new(),
+            };
+            if let Some(data) = node.children().find(|child| child.has_tag_name("data")) {
+                layer.encoding = data.attribute("encoding").map(str::to_string);
+                layer.data = match layer.encoding.as_deref() {
+                    Some("csv") => Some(LevelMap::tmx_csv(&layer.name, data.text().unwrap_or_default())?),
+                    // left for tiles to turn down
+                    Some(_) => None,
+                    // the old format with a <tile gid="..."/> per tile
+                    None => Some(Value::Array(
+                        data.children()
+                            .filter(|tile| tile.has_tag_name("tile"))
+                            .map(|tile| LevelMap::tmx_number(tile, "gid", Some(0u64)).map(Value::from))
+                            .collect::<Result<_, _>>()?,
+                    )),
+                };
+            }
+            for object in node.children().filter(|child| child.has_tag_name("object")) {
+                layer.objects.push(ObjectFile {
+                    id: LevelMap::tmx_number(object, "id", Some(0))?,
+                    x: LevelMap::tmx_number(object, "x", None)?,
+                    y: LevelMap::tmx_number(object, "y", None)?,
+                    width: LevelMap::tmx_number(object, "width", Some(0.0))?,
+                    height: LevelMap::tmx_number(object, "height", Some(0.0))?,
+                    properties: LevelMap::tmx_properties(object),
+                });
+            }
+            layers.push(layer);
+        }
+
+        Ok(MapFile {
+            orientation: map.attribute("orientation").ok_or("the <map> has no orientation")?.to_string(),
+            infinite: map.attribute("infinite") == Some("1"),
+            width: LevelMap::tmx_number(map, "width", None)?,
+            height: LevelMap::tmx_number(map, "height", None)?,
+            tilewidth: LevelMap::tmx_number(map, "tilewidth", None)?,
+            tileheight: LevelMap::tmx_number(map, "tileheight", None)?,
+            layers,
+        })
+    }
+
+    // an attribute that has to parse as T, default is used when it's missing
+    fn tmx_number<T: std::str::FromStr>(node: Node, name: &str, default: Option<T>) -> Result<T, String> {
+        let tag = node.tag_name().name();
+        match node.attribute(name) {
+            Some(value) => value.parse().map_err(|_| format!("<{}> has {}=\"{}\", it should be a number", tag, name, value)),
+            None => default.ok_or_else(|| format!("a <{}> is missing its {}", tag, name)),
+        }
+    }
+
+    fn tmx_csv(layer: &str, text: &str) -> Result<Value, String> {
+        text.split(',')
+            .map(str::trim)
+            .filter(|gid| !gid.is_empty())
+            .map(|gid| {
+                gid.parse::<u64>()
+                    .map(Value::from)
+                    .map_err(|_| format!("layer \"{}\" has {} where a tile id should be", layer, gid))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array)
+    }
+
+    // typed like the json would be, a value that doesn't parse is kept as a string for from_file to complain about
+    fn tmx_properties(object: Node) -> Vec<PropertyFile> {
+        let Some(properties) = object.children().find(|child| child.has_tag_name("properties")) else {
+            return Vec::new();
+        };
+        properties
+            .children()
+            .filter(|property| property.has_tag_name("property"))
+            .map(|property| {
+                // long strings go in the text instead of the value attribute
+                let text = property.attribute("value").or(property.text()).unwrap_or_default();
+                let value = match property.attribute("type") {
+                    Some("int") => text.parse::<i64>().map(Value::from).unwrap_or_else(|_| Value::from(text)),
+                    Some("float") => text.parse::<f64>().map(Value::from).unwrap_or_else(|_| Value::from(text)),
+                    Some("bool") => Value::from(text == "true"),
+                    _ => Value::from(text),
+                };
+                PropertyFile {
+                    name: property.attribute("name").unwrap_or_default().to_string(),
+                    value,
+                }
+            })
+            .collect()
+    }
+
+    fn from_file(map: MapFile) -> Result<LevelMap, String> {
+        if map.orientation != "orthogonal" {
+            return Err(format!("{} maps aren't supported, it has to be orthogonal", map.orientation));
+        }
+        if map.infinite {
+            return Err("infinite maps aren't supported, untick Infinite in the map properties".to_string());
+        }
+        if !(map.tilewidth > 0.0 && map.tileheight > 0.0) {
+            return Err("tiles need a width and height above zero".to_string());
+        }
+        let pixel_width = map.width as f32 * map.tilewidth;
+        if pixel_width > WINDOW_WIDTH as f32 {
+            return Err(format!("it's {} pixels wide but the window is only {}", pixel_width, WINDOW_WIDTH));
+        }
+        // moves map coordinates so the bottom of the map is the bottom of the window
+        let offset = Vec2::new(0.0, WINDOW_HEIGHT as f32 - map.height as f32 * map.tileheight);
+        let top = offset.y.min(0.0);
+
+        let mut level = LevelMap {
+            platforms: Vec::new(),
+            bricks: Vec::new(),
+            player_spawn: Vec2::new(0.0, 0.0),
+            tokens: Vec::new(),
+            bat_zones: Vec::new(),
+            bounds: Aabb::new(Vec2::new(0.0, top), Vec2::new(WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32 - top)),
+        };
+        let mut player_spawn = None;
+
+        for layer in &map.layers {
+            match (layer.kind.as_str(), layer.name.as_str()) {
+                ("tilelayer", "platforms") => level.platforms.extend(LevelMap::tiles(&map, layer, offset)?),
+                ("tilelayer", "bricks") => level.bricks.extend(LevelMap::tiles(&map, layer, offset)?),
+                ("objectgroup", "player") => {
+                    for object in &layer.objects {
+                        if player_spawn.is_some() {
+                            return Err(format!("object {} is a second player spawn, there can only be one", object.id));
+                        }
+                        player_spawn = Some(Vec2::new(object.x, object.y) + offset);
+                    }
+                }
+                ("objectgroup", "tokens") => {
+                    level.tokens.extend(layer.objects.iter().map(|object| Vec2::new(object.x, object.y) + offset));
+                }
+                ("objectgroup", "bats") => {
+                    for object in &layer.objects {
+                        let area = Aabb::new(Vec2::new(object.x, object.y) + offset, Vec2::new(object.width, object.height));
+                        level.bat_zones.push(BatZone { area, count: LevelMap::count(object)? });
+                    }
+                }
+                ("tilelayer", name) | ("objectgroup", name) => {
+                    return Err(format!(
+                        "don't know what to do with {} layer \"{}\", expected tile layers \"platforms\" or \"bricks\" and object layers \"player\", \"tokens\" or \"bats\"",
+                        layer.kind, name
+                    ));
+                }
+                // image layers and groups are just for the editor
+                _ => {}
+            }
+        }
+
+        // bats are numbered with a u16, the same cap as the difficulty table
+        let bats = level.bat_zones.iter().try_fold(0usize, |total, zone| total.checked_add(zone.count));
+        if !matches!(bats, Some(bats) if bats <= u16::MAX as usize) {
+            return Err(format!("the bat zones hold more than {} bats between them", u16::MAX));
+        }
+
+        level.player_spawn = player_spawn.ok_or("it needs an object layer called \"player\" with the spawn point in it")?;
+        if level.player_spawn.y < 0.0 || level.player_spawn.y >= WINDOW_HEIGHT as f32 {
+            return Err("the player spawn has to be within the bottom window height of the map".to_string());
+        }
+        Ok(level)
+    }
+
+    // one box per tile, anything that isn't 0 is solid whatever tile it is
+    fn tiles(map: &MapFile, layer: &LayerFile, offset: Vec2<f32>) -> Result<Vec<Aabb>, String> {
+        if let Some(encoding) = &layer.encoding {
+            if encoding != "csv" {
+                return Err(format!("layer \"{}\" is {} encoded, set the tile layer format to CSV", layer.name, encoding));
+            }
+        }
+        let data = match &layer.data {
+            Some(Value::Array(data)) => data,
+            _ => return Err(format!("layer \"{}\" has no tile data", layer.name)),
+        };
+        if data.len() != map.width * map.height {
+            return Err(format!(
+                "layer \"{}\" has {} tiles but a {}x{} map needs {}",
+                layer.name,
+                data.len(),
+                map.width,
+                map.height,
+                map.width * map.height
+            ));
+        }
+
+        let size = Vec2::new(map.tilewidth, map.tileheight);
+        let mut tiles = Vec::new();
+        for (i, tile) in data.iter().enumerate() {
+            let gid = tile
+                .as_u64()
+                .ok_or_else(|| format!("layer \"{}\" has {} where a tile id should be", layer.name, tile))?;
+            if gid != 0 {
+                let cell = Vec2::new((i % map.width) as f32, (i / map.width) as f32);
+                tiles.push(Aabb::new(cell * size + offset, size));
+            }
+        }
+        Ok(tiles)
+    }
+
+    fn count(object: &ObjectFile) -> Result<usize, String> {
+        match object.properties.iter().find(|property| property.name == "count") {
+            None => Ok(1),
+            Some(property) => property
+                .value
+                .as_u64()
+                .map(|count| count as usize)
+                .ok_or_else(|| format!("bat zone {} has count {}, it should be a whole number", object.id, property.value)),
+        }
+    }
+
+    pub fn bats(&self) -> usize {
+        self.bat_zones.iter().map(|zone| zone.count).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LevelMap, MapFile};
+
+    // a 2x2 map with one solid tile, a spawn point and whatever bat zones are given
+    fn map_json(bat_zones: &str) -> String {
+        format!(
+            r#"{{
+                "orientation": "orthogonal", "width": 2, "height": 2, "tilewidth": 32, "tileheight": 16,
+                "layers": [
+                    {{ "name": "platforms", "type": "tilelayer", "data": [0, 0, 1, 0] }},
+                    {{ "name": "player", "type": "objectgroup", "objects": [{{ "id": 1, "x": 4, "y": 8 }}] }},
+                    {{ "name": "bats", "type": "objectgroup", "objects": [{}] }}
+                ]
+            }}"#,
+            bat_zones
+        )
+    }
+
+    fn bat_zone(id: u32, count: u64) -> String {
+        format!(
+            r#"{{ "id": {}, "x": 0, "y": 0, "width": 64, "height": 16, "properties": [{{ "name": "count", "type": "int", "value": {} }}] }}"#,
+            id, count
+        )
+    }
+
+    fn parse(json: &str) -> Result<LevelMap, String> {
+        let map: MapFile = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        LevelMap::from_file(map)
+    }
+
+    #[test]
+    fn bat_zones_add_up() {
+        let level = parse(&map_json(&format!("{}, {}", bat_zone(2, 3), bat_zone(3, 4)))).unwrap();
+        assert_eq!(level.bats(), 7);
+        assert_eq!(level.platforms.len(), 1);
+    }
+
+    #[test]
+    fn too_many_bats_is_an_error() {
+        let at_cap = parse(&map_json(&format!("{}, {}", bat_zone(2, 65000), bat_zone(3, 535)))).unwrap();
+        assert_eq!(at_cap.bats(), u16::MAX as usize);
+
+        let error = parse(&map_json(&format!("{}, {}", bat_zone(2, 65000), bat_zone(3, 536)))).unwrap_err();
+        assert!(error.contains("more than 65535 bats"), "{}", error);
+        // big enough to overflow when added up
+        let error = parse(&map_json(&format!("{}, {}", bat_zone(2, u64::MAX), bat_zone(3, 1)))).unwrap_err();
+        assert!(error.contains("more than 65535 bats"), "{}", error);
+    }
+
+    #[test]
+    fn tmx_and_json_examples_match() {
+        let json = LevelMap::load("resources/levels/example.json").unwrap();
+        let tmx = LevelMap::load("resources/levels/example.tmx").unwrap();
+        assert_eq!(json, tmx);
+        assert_eq!(tmx.bats(), 30);
+        assert_eq!(tmx.tokens.len(), 3);
+    }
+
+    fn parse_tmx(layers: &str) -> Result<LevelMap, String> {
+        let text = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <map orientation="orthogonal" width="2" height="2" tilewidth="32" tileheight="16" infinite="0">
+             <objectgroup name="player"><object id="1" x="4" y="8"/></objectgroup>
+             {}
+            </map>"#,
+            layers
+        );
+        LevelMap::from_file(LevelMap::from_tmx(&text)?)
+    }
+
+    #[test]
+    fn tmx_tile_formats() {
+        let csv = parse_tmx(r#"<layer name="platforms"><data encoding="csv">0,0,
+            1,1</data></layer>"#).unwrap();
+        let elements = parse_tmx(r#"<layer name="platforms"><data><tile/><tile/><tile gid="1"/><tile gid="1"/></data></layer>"#).unwrap();
+        assert_eq!(csv.platforms.len(), 2);
+        assert_eq!(csv, elements);
+
+        let error = parse_tmx(r#"<layer name="platforms"><data encoding="base64" compression="zlib">eJxjYGBgAAAABAAB</data></layer>"#).unwrap_err();
+        assert!(error.contains("base64 encoded"), "{}", error);
+        let error = parse_tmx(r#"<layer name="platforms"><data encoding="csv">0,0,1</data></layer>"#).unwrap_err();
+        assert!(error.contains("has 3 tiles"), "{}", error);
+    }
+
+    #[test]
+    fn tmx_objects() {
+        let level = parse_tmx(
+            r#"<objectgroup name="tokens"><object id="2" x="10" y="12"/></objectgroup>
+               <objectgroup name="bats">
+                <object id="3" x="0" y="0" width="64" height="16">
+                 <properties><property name="count" type="int" value="5"/></properties>
+                </object>
+               </objectgroup>"#,
+        )
+        .unwrap();
+        assert_eq!(level.tokens.len(), 1);
+        assert_eq!(level.bats(), 5);
+
+        let error = parse_tmx(
+            r#"<objectgroup name="bats"><object id="3" x="0" y="0">
+                <properties><property name="count" type="int" value="-5"/></properties>
+               </object></objectgroup>"#,
+        )
+        .unwrap_err();
+        assert!(error.contains("whole number"), "{}", error);
+        let error = parse_tmx(r#"<objectgroup name="tokens"><object id="2" y="12"/></objectgroup>"#).unwrap_err();
+        assert!(error.contains("missing its x"), "{}", error);
+        let error = parse_tmx(r#"<objectgroup name="doors"/>"#).unwrap_err();
+        assert!(error.contains("\"doors\""), "{}", error);
+        assert!(LevelMap::from_tmx("<map").is_err());
+    }
+}
@@ -1,12 +1,26 @@
-use enemy::Enemy;
-use player::Player;
-use platform::Platform;
-use token::Token;
-use tetra::graphics::{self, Color, Texture};
-use tetra::graphics::text::{Font, Text};
-use tetra::input;
+use std::path::PathBuf;
+
+use animation::SpriteSheet;
+use assets::Assets;
+use audio::{Audio, Effect};
+use controls::{Action, Bindings, ControlsScreen};
+use debug_overlay::DebugOverlay;
+use game_object::Textures;
+use high_scores::{HighScore, HighScores};
+use hud::Hud;
+use input_source::{GamepadInput, InputSource, KeyboardInput};
+use options::Options;
+use particles::Particles;
+use rand::Rng;
+use replay::{Replay, ReplayWriter, RunConfig};
+use scene::{Scene, SceneStack};
+use simulation::{GameEvent, InputFrame, Simulation};
+use tetra::graphics::{self, Color};
+use tetra::graphics::text::Text;
+use tetra::input::{self, Key};
 use tetra::math::Vec2;
-use tetra::{Context, ContextBuilder, State};
+use tetra::time::{self, Timestep};
+use tetra::{Context, ContextBuilder, State, TetraError};
 
 pub mod player;
 pub mod platform;
@@ -14,109 +28,383 @@ pub mod enemy;
 pub mod token;
 pub mod game_object;
 pub mod queue;
+pub mod simulation;
+pub mod options;
+pub mod replay;
+pub mod spatial_hash;
+pub mod bench;
+pub mod flock_config;
+pub mod debug_overlay;
+pub mod collision;
+pub mod scene;
+pub mod high_scores;
+pub mod hud;
+pub mod difficulty;
+pub mod platform_generator;
+pub mod level_map;
+pub mod assets;
+pub mod controls;
+pub mod input_source;
+pub mod animation;
+pub mod particles;
+pub mod camera;
+pub mod audio;
+pub mod config;
 
 const PLATFORM_NUM: usize = 35;
+// bats at the start of a run, the difficulty table adds more later
 const ENEMY_NUM: usize = 40;
 const TOKEN_NUM: usize = 5;
-const POSITION_BUFFER_SIZE: usize = 100;
+// updates per second, gameplay constants are all per second so this only changes precision
+const TICK_RATE: f64 = 60.0;
+const PLAYER_TRAIL_SECONDS: f32 = 1.65;
+// the game keeps running this long after the player dies before the game over screen
+const DEATH_SECONDS: f64 = 1.0;
 const BAT_VISUAL_RANGE: f32 = 100.0;
-const DOWN_SPEED_INITIAL: f32 = 0.4;
+// pixels per second
+const DOWN_SPEED_INITIAL: f32 = 24.0;
+const RESOURCES: &str = "./resources";
+const TEXTURES: [&str; 7] = [
+    "player-sheet.png",
+    "health.png",
+    "platform.png",
+    "brick.png",
+    "token.png",
+    "bat-sheet.png",
+    "wabbit_alpha.png",
+];
+const FONT: &str = "DejaVuSansMono.fnt";
+const SOUNDS: [&str; 6] = [
+    Effect::Jump.file(),
+    Effect::Land.file(),
+    Effect::Token.file(),
+    Effect::BatHit.file(),
+    Effect::Death.file(),
+    audio::MUSIC,
+];
 pub const WINDOW_WIDTH: i32 = 640;
 pub const WINDOW_HEIGHT: i32 = 480;
 
 
+// thin adapter between tetra and the simulation: samples the keyboard and draws
 struct GameState {
-    player: Player,
-    platforms: Vec<Platform>,
-    enemies: Vec<Enemy>,
-    tokens: Vec<Token>,
-    down_speed: f32
+    simulation: Simulation,
+    textures: Textures,
+    scenes: SceneStack,
+    title_text: Text,
+    pause_text: Text,
+    game_over_text: Text,
+    bindings: Bindings,
+    // where rebinding saves to
+    bindings_path: Option<PathBuf>,
+    controls_screen: ControlsScreen,
+    high_scores: HighScores,
+    // None when there's nowhere to keep them, the table then only lasts until the game closes
+    high_scores_path: Option<PathBuf>,
+    hud: Hud,
+    particles: Particles,
+    audio: Audio,
+    debug_overlay: DebugOverlay,
+    recorder: Option<ReplayWriter>,
+    playback: Option<Replay>,
+    tick: usize,
+    // the world has been played on since it was built, so the next run has to start over
+    needs_restart: bool,
+    // ticks since the player died
+    dead_ticks: u32,
 }
 
 impl GameState {
-    fn new(ctx: &mut Context) -> tetra::Result<GameState> {
+    fn new(ctx: &mut Context, options: &Options) -> tetra::Result<GameState> {
+        // first, so nothing like a replay file gets created if the game can't start
+        let assets = Assets::load(ctx, RESOURCES, &TEXTURES, &[FONT], &SOUNDS)?;
 
-                
-        let restart_text = Text::new(
-            "     YOU DIED!\nPRESS \"R\" TO RESTART",
-            Font::bmfont(ctx, "./resources/DejaVuSansMono.fnt")?,
-        );
+        let playback = match &options.replay {
+            Some(path) => Some(Replay::load(path).map_err(|reason| TetraError::FailedToLoadAsset {
+                reason,
+                path: path.clone(),
+            })?),
+            None => None,
+        };
 
-        let mut textures = Vec::new();
-        textures.push(Texture::new(ctx, "./resources/player-l.png")?);
-        textures.push(Texture::new(ctx, "./resources/player.png")?);
-        textures.push(Texture::new(ctx, "./resources/health.png")?);
-        let start_pos = Vec2::new(32.0, 32.0);
-        let player = Player::new(textures, start_pos, restart_text);
-
-        let mut platforms = Vec::with_capacity(PLATFORM_NUM);
-        let mut enemies = Vec::with_capacity(ENEMY_NUM);
-        let mut tokens: Vec<Token> = Vec::with_capacity(TOKEN_NUM);
-        let mut rng = rand::thread_rng();
-        let mut next_available_id: u16 = 0;
-
-        // first platform under player
-        platforms.push(Platform::new(Texture::new(ctx, "./resources/platform.png")?, Vec2::new(78.0,50.0), false, Vec2::new(0.0, 148.0)));
-        for _ in 1..PLATFORM_NUM {
-            platforms.push(Platform::new(Texture::new(ctx, "./resources/platform.png")?, Vec2::new(78.0,50.0), true, Vec2::new(0.0, 0.0)));
-        }
+        // a replay always runs on the seed it was recorded with
+        let seed = playback
+            .as_ref()
+            .map(Replay::seed)
+            .or(options.seed)
+            .unwrap_or_else(|| rand::thread_rng().gen());
+        let tick_rate = playback
+            .as_ref()
+            .map(Replay::tick_rate)
+            .or(options.tick_rate)
+            .unwrap_or(TICK_RATE);
+        time::set_timestep(ctx, Timestep::Fixed(tick_rate));
+        println!("Seed: {}, tick rate: {} Hz", seed, tick_rate);
 
-        for _ in 0..TOKEN_NUM {
-            tokens.push(Token::new(Texture::new(ctx, "./resources/token.png")?, Vec2::new(15.0,16.0), true, Vec2::new(0.0,0.0)));
-        }
+        // and on the configs and level it was recorded with
+        let config = match &playback {
+            Some(replay) => replay.config().clone(),
+            None => RunConfig {
+                flock_config: options.load_flock_config()?,
+                difficulty: options.load_difficulty()?,
+                level: options.load_level()?,
+            },
+        };
 
-        let mut bat_textures = Vec::new();
-        bat_textures.push(Texture::new(ctx, "./resources/bat-l.png")?);
-        bat_textures.push(Texture::new(ctx, "./resources/bat-r.png")?);
-        bat_textures.push(Texture::new(ctx, "./resources/wabbit_alpha.png")?);
+        let recorder = match &options.record {
+            Some(path) => Some(ReplayWriter::create(path, seed, tick_rate, &config).map_err(|e| {
+                TetraError::PlatformError(format!("failed to create replay {}: {}", path.display(), e))
+            })?),
+            None => None,
+        };
 
-        for _ in 0..ENEMY_NUM-1 {
-            enemies.push(Enemy::new(next_available_id, bat_textures.clone(), Vec2::new(20.0,20.0), &mut rng, BAT_VISUAL_RANGE, false));
-            next_available_id += 1;
-        }
-        // set debug to true for one bat's debug console out
-        enemies.push(Enemy::new(next_available_id, bat_textures.clone(), Vec2::new(20.0,20.0), &mut rng, BAT_VISUAL_RANGE, true));
-        
-        let mut textures = Vec::new();
-        textures.push(Texture::new(ctx, "./resources/player-l.png")?);
-        textures.push(Texture::new(ctx, "./resources/player.png")?);
-        textures.push(Texture::new(ctx, "./resources/health.png")?);
+        let font = assets.font(FONT);
+        let title_text = Text::new("       TETRAS\n\nPRESS ENTER TO START\n\n\"C\" FOR CONTROLS", font.clone());
+        let pause_text = Text::new("", font.clone());
+        let bindings = options.load_controls()?;
+        let controls_screen = ControlsScreen::new(font.clone());
+        let game_over_text = Text::new("", font.clone());
+
+        let high_scores_path = HighScores::default_path();
+        let high_scores = high_scores_path.as_ref().map(HighScores::load).unwrap_or_default();
+
+        let textures = Textures {
+            player: SpriteSheet::new(assets.texture("player-sheet.png"), 16.0, 16.0),
+            platform: assets.texture("platform.png"),
+            brick: assets.texture("brick.png"),
+            token: assets.texture("token.png"),
+            bat: SpriteSheet::new(assets.texture("bat-sheet.png"), 15.0, 16.0),
+        };
+
+        let hud = Hud::new(font.clone(), assets.texture("health.png"));
+        let can_freeze = recorder.is_none() && playback.is_none();
+        let debug_overlay = DebugOverlay::new(font, assets.texture("wabbit_alpha.png"), can_freeze);
+
+        let mut audio = Audio::open(ctx, &assets, options.load_audio()?, options.mute);
+        audio.play_music();
 
         Ok(GameState {
-            player,
-            platforms,
-            enemies,
-            tokens,
-            down_speed: DOWN_SPEED_INITIAL
+            simulation: Simulation::new(seed, config.flock_config, config.difficulty, tick_rate, config.level),
+            textures,
+            // a replay has no title screen to wait on
+            scenes: SceneStack::new(if playback.is_some() { Scene::Playing } else { Scene::Title }),
+            title_text,
+            pause_text,
+            game_over_text,
+            bindings,
+            bindings_path: options.controls_path(),
+            controls_screen,
+            high_scores,
+            high_scores_path,
+            hud,
+            particles: Particles::new(ctx)?,
+            audio,
+            debug_overlay,
+            recorder,
+            playback,
+            tick: 0,
+            needs_restart: false,
+            dead_ticks: 0,
         })
     }
-}
 
-impl State for GameState {
-    fn update(&mut self, ctx: &mut Context) -> tetra::Result {
-        let n = self.enemies.len();
-        let mut boids_pos:Vec<Vec2<f32>> = vec![Vec2::new(0.0,0.0); n];
-        let mut boids_vel:Vec<Vec2<f32>> = vec![Vec2::new(0.0,0.0); n];
-        if n > 0 {
-            for i in 0..(self.enemies).len() {
-                boids_pos[i] = self.enemies[i].get_pos();
-                boids_vel[i] = self.enemies[i].get_vel();
+    // menu actions come from the keyboard bindings or the matching pad button
+    fn is_pressed(&self, ctx: &Context, action: Action) -> bool {
+        self.bindings.is_pressed(ctx, action) || GamepadInput::is_pressed(ctx, action)
+    }
+
+    // one tick of the game itself, only while the Playing scene is on top
+    fn step_simulation(&mut self, ctx: &mut Context) -> tetra::Result {
+        let frame = match &self.playback {
+            Some(replay) => {
+                if self.tick == replay.len() {
+                    println!("Replay finished after {} ticks", replay.len());
+                }
+                replay.frame(self.tick).copied().unwrap_or_default()
             }
+            None => {
+                let mut frame = read_input(ctx, &self.bindings);
+                // goes through the input so the restart ends up in a recording too
+                frame.restart |= std::mem::take(&mut self.needs_restart);
+                frame
+            }
+        };
+
+        if let Some(recorder) = &mut self.recorder {
+            recorder.record(&frame).map_err(|e| {
+                TetraError::PlatformError(format!("failed to write replay: {}", e))
+            })?;
         }
 
-        // UPDATES HERE
-        for i in 0..self.platforms.len() {
-            self.platforms[i].update(self.down_speed);
+        self.simulation.step(&frame);
+        self.tick += 1;
+        if frame.restart {
+            self.particles.clear();
+            self.debug_overlay.clear_selection();
+        }
+        self.particles.update((1.0 / self.simulation.tick_rate()) as f32);
+        for event in self.simulation.events() {
+            match *event {
+                GameEvent::TokenCollected { position, .. } => self.particles.emit(&particles::TOKEN_PICKUP, position),
+                GameEvent::PlayerHurt { position } => self.particles.emit(&particles::BAT_HIT, position),
+                GameEvent::PlayerDied { position } => self.particles.emit(&particles::PLAYER_DEATH, position),
+                GameEvent::Jumped { .. } | GameEvent::Landed { .. } => {}
+            }
+        }
+        self.audio.handle(self.simulation.events());
+        self.debug_overlay.update(ctx, &mut self.simulation);
+        self.hud.update(&self.simulation);
+
+        if self.simulation.player().is_dead() {
+            // lets the death play out before the game over screen covers it
+            self.dead_ticks += 1;
+            if self.dead_ticks as f64 >= DEATH_SECONDS * self.simulation.tick_rate() {
+                self.game_over(ctx);
+            }
+        } else {
+            self.dead_ticks = 0;
         }
-        for i in 0..self.tokens.len() {
-            self.tokens[i].update(self.down_speed);
+        Ok(())
+    }
+
+    fn game_over(&mut self, ctx: &Context) {
+        let score = self.simulation.player().get_score();
+        let seed = self.simulation.seed();
+
+        // watching a replay isn't a new run, so it doesn't go on the table
+        let rank = if self.playback.is_none() {
+            let rank = self.high_scores.insert(HighScore::now(score, self.simulation.elapsed(), seed));
+            if let (Some(_), Some(path)) = (rank, &self.high_scores_path) {
+                if let Err(e) = self.high_scores.save(path) {
+                    eprintln!("couldn't save high scores to {}: {}", path.display(), e);
+                }
+            }
+            rank
+        } else {
+            None
+        };
+
+        let mut content = format!(
+            "YOU DIED!  SCORE: {}  TIME: {:.1}s\nSEED: {}\n\nHIGH SCORES\n",
+            score,
+            self.simulation.elapsed(),
+            seed
+        );
+        for (i, entry) in self.high_scores.entries().iter().enumerate() {
+            content.push_str(&format!(
+                "{:>2}. {:>4} {:>7.1}s  {}  {:<20}{}\n",
+                i + 1,
+                entry.score,
+                entry.survival_time,
+                entry.date_string(),
+                entry.seed,
+                if rank == Some(i) { " <" } else { "" }
+            ));
         }
-        let player_oldest_pos = self.player.oldest_player_pos();
-        for j in 0..self.enemies.len() {
-            self.enemies[j].update(player_oldest_pos, &boids_pos, &boids_vel)
+        content.push_str(&format!("\nPRESS {} TO CONTINUE", self.bindings.describe(ctx, Action::Restart)));
+        self.game_over_text.set_content(content);
+
+        self.scenes.replace(Scene::GameOver { score });
+        self.needs_restart = true;
+    }
+
+    fn draw_scene(&mut self, ctx: &mut Context, scene: Scene, blend: f32) -> tetra::Result {
+        let center = Vec2::new(WINDOW_WIDTH as f32 / 2.0, WINDOW_HEIGHT as f32 / 2.0);
+        match scene {
+            Scene::Title => {
+                graphics::clear(ctx, Color::rgb(0.0, 0.0, 0.0));
+                self.title_text.draw(ctx, center - Vec2::new(90.0, 32.0));
+            }
+            Scene::Playing => {
+                graphics::clear(ctx, Color::rgb(0.769, 0.812, 0.631));
+                // the world is drawn through the camera, the hud straight onto the window
+                graphics::set_transform_matrix(ctx, self.simulation.camera().transform(blend));
+                for object in self.simulation.objects() {
+                    object.draw(ctx, &self.textures, blend);
+                }
+                self.particles.draw(ctx, blend);
+                self.debug_overlay.draw_world(ctx, &self.simulation)?;
+                graphics::reset_transform_matrix(ctx);
+                self.debug_overlay.draw(ctx, &self.simulation);
+                self.hud.draw(ctx, &self.simulation);
+            }
+            Scene::Controls => {
+                graphics::clear(ctx, Color::rgb(0.0, 0.0, 0.0));
+                self.controls_screen.draw(ctx, &self.bindings);
+            }
+            Scene::Paused => {
+                self.pause_text.draw(ctx, center - Vec2::new(90.0, 32.0));
+            }
+            Scene::GameOver { .. } => {
+                graphics::clear(ctx, Color::rgb(0.0, 0.0, 0.0));
+                self.game_over_text.draw(ctx, Vec2::new(24.0, 40.0));
+            }
         }
+        Ok(())
+    }
+}
 
-        self.player.update(ctx, self.platforms.clone(), self.enemies.clone(), self.tokens.clone());
+// the keyboard and every connected pad, so either can be picked up mid run
+fn read_input(ctx: &Context, bindings: &Bindings) -> InputFrame {
+    let keyboard = KeyboardInput::new(ctx, bindings).next_frame();
+    keyboard.merge(GamepadInput::new(ctx, input_source::DEAD_ZONE).next_frame())
+}
+
+impl State for GameState {
+    fn update(&mut self, ctx: &mut Context) -> tetra::Result {
+        match self.scenes.current() {
+            Scene::Title => {
+                if input::is_key_pressed(ctx, Key::Enter)
+                    || input::is_key_pressed(ctx, Key::Space)
+                    || GamepadInput::is_pressed(ctx, Action::Pause)
+                {
+                    self.scenes.replace(Scene::Playing);
+                } else if input::is_key_pressed(ctx, Key::C) {
+                    self.scenes.push(Scene::Controls);
+                }
+            }
+            Scene::Controls => {
+                if self.controls_screen.update(ctx, &mut self.bindings) {
+                    if let Some(path) = &self.bindings_path {
+                        if let Err(e) = self.bindings.save(path) {
+                            eprintln!("couldn't save controls to {}: {}", path.display(), e);
+                        }
+                    }
+                    self.scenes.pop();
+                }
+            }
+            Scene::Playing => {
+                if self.is_pressed(ctx, Action::Pause) {
+                    let pause = self.bindings.describe(ctx, Action::Pause);
+                    self.pause_text.set_content(format!("       PAUSED\n\nPRESS {} TO RESUME", pause));
+                    self.scenes.push(Scene::Paused);
+                    self.audio.pause_music();
+                } else {
+                    self.step_simulation(ctx)?;
+                }
+            }
+            Scene::Paused => {
+                if self.is_pressed(ctx, Action::Pause) {
+                    self.scenes.pop();
+                    self.audio.play_music();
+                }
+            }
+            Scene::GameOver { .. } => {
+                match &self.playback {
+                    // a replay carries on into its next run by itself, the restart is in the recording
+                    Some(replay) => {
+                        if self.tick < replay.len() {
+                            self.scenes.replace(Scene::Playing);
+                        }
+                    }
+                    None => {
+                        if self.is_pressed(ctx, Action::Restart) || input::is_key_pressed(ctx, Key::Enter) {
+                            self.scenes.replace(Scene::Title);
+                        }
+                    }
+                }
+            }
+        }
 
         let mut pressed = input::get_keys_pressed(ctx).peekable();
         if pressed.peek().is_some() {
@@ -138,29 +426,35 @@ impl State for GameState {
     }
 
     fn draw(&mut self, ctx: &mut Context) -> tetra::Result {
-        graphics::clear(ctx, Color::rgb(0.769, 0.812, 0.631));
-        
-        for platform in &self.platforms {
-            platform.draw(ctx);
-        }
+        // draw everything partway between the last two ticks so motion is smooth at any tick rate
+        let blend = time::get_blend_factor(ctx);
 
-        for token in &self.tokens {
-            token.draw(ctx);
-        }
-        
-        for enemy in &self.enemies {
-            enemy.draw(ctx);
+        let scenes: Vec<Scene> = self.scenes.iter().copied().collect();
+        for scene in scenes {
+            self.draw_scene(ctx, scene, blend)?;
         }
-        
-        self.player.draw(ctx);
 
         Ok(())
     }
 }
 
 fn main() -> tetra::Result {
+    let options = match Options::from_args() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("{}", options::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if options.bench_boids {
+        bench::run_boid_benchmark(options.seed.unwrap_or(0), &options.load_flock_config()?);
+        return Ok(());
+    }
+
     ContextBuilder::new("Keyboard Input", WINDOW_WIDTH, WINDOW_HEIGHT)
         .quit_on_escape(true)
         .build()?
-        .run(GameState::new)
-}
\ No newline at end of file
+        .run(|ctx| GameState::new(ctx, &options))
+}
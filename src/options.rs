@@ -0,0 +1,119 @@
+use std::env;
+use std::path::PathBuf;
+
+use super::audio::AudioSettings;
+use super::config;
+use super::controls::Bindings;
+use super::difficulty::Difficulty;
+use super::flock_config::FlockConfig;
+use super::level_map::LevelMap;
+
+const DEFAULT_FLOCK_CONFIG: &str = "./resources/flock.toml";
+const DEFAULT_DIFFICULTY: &str = "./resources/difficulty.toml";
+const DEFAULT_CONTROLS: &str = "./resources/controls.toml";
+const DEFAULT_AUDIO: &str = "./resources/audio.toml";
+
+pub const USAGE: &str = "usage: tetras [--seed <u64>] [--record <file> | --replay <file>] [--tick-rate <hz>] [--flock-config <file>] [--difficulty <file>] [--level <map.json|map.tmx>] [--controls <file>] [--audio <file>] [--mute] [--bench-boids]";
+
+// command line flags, e.g. `tetras --seed 1234 --record bug.replay`
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub seed: Option<u64>,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub tick_rate: Option<f64>,
+    pub flock_config: Option<PathBuf>,
+    pub difficulty: Option<PathBuf>,
+    pub level: Option<PathBuf>,
+    pub controls: Option<PathBuf>,
+    pub audio: Option<PathBuf>,
+    // no sound at all, the volumes in the audio settings are ignored
+    pub mute: bool,
+    pub bench_boids: bool,
+}
+
+impl Options {
+    // an explicit --flock-config must exist, otherwise the bundled file is used if it's there
+    pub fn load_flock_config(&self) -> tetra::Result<FlockConfig> {
+        config::load_or_default(self.flock_config.as_deref(), DEFAULT_FLOCK_CONFIG, |path| FlockConfig::load(path))
+    }
+
+    // same rules as the flock config
+    pub fn load_difficulty(&self) -> tetra::Result<Difficulty> {
+        config::load_or_default(self.difficulty.as_deref(), DEFAULT_DIFFICULTY, |path| Difficulty::load(path))
+    }
+
+    // None means a random layout
+    pub fn load_level(&self) -> tetra::Result<Option<LevelMap>> {
+        self.level.as_ref().map(LevelMap::load).transpose()
+    }
+
+    // where rebinding in game saves to, --controls if given
+    pub fn controls_path(&self) -> Option<PathBuf> {
+        self.controls.clone().or_else(Bindings::user_path)
+    }
+
+    // --controls, then whatever was last saved in game, then the bundled file
+    pub fn load_controls(&self) -> tetra::Result<Bindings> {
+        let path = self.controls_path().filter(|path| self.controls.is_some() || path.exists());
+        config::load_or_default(path.as_deref(), DEFAULT_CONTROLS, |path| Bindings::load(path))
+    }
+
+    // same rules as the flock config
+    pub fn load_audio(&self) -> tetra::Result<AudioSettings> {
+        config::load_or_default(self.audio.as_deref(), DEFAULT_AUDIO, |path| AudioSettings::load(path))
+    }
+
+    pub fn from_args() -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let value = args.next().ok_or("--seed needs a value")?;
+                    let seed = value.parse().map_err(|_| format!("invalid seed \"{}\"", value))?;
+                    options.seed = Some(seed);
+                }
+                "--record" => {
+                    options.record = Some(args.next().ok_or("--record needs a file")?.into());
+                }
+                "--replay" => {
+                    options.replay = Some(args.next().ok_or("--replay needs a file")?.into());
+                }
+                "--tick-rate" => {
+                    let value = args.next().ok_or("--tick-rate needs a value")?;
+                    match value.parse::<f64>() {
+                        Ok(rate) if rate > 0.0 && rate.is_finite() => options.tick_rate = Some(rate),
+                        _ => return Err(format!("invalid tick rate \"{}\"", value)),
+                    }
+                }
+                "--flock-config" => {
+                    options.flock_config = Some(args.next().ok_or("--flock-config needs a file")?.into());
+                }
+                "--difficulty" => {
+                    options.difficulty = Some(args.next().ok_or("--difficulty needs a file")?.into());
+                }
+                "--level" => {
+                    options.level = Some(args.next().ok_or("--level needs a file")?.into());
+                }
+                "--controls" => {
+                    options.controls = Some(args.next().ok_or("--controls needs a file")?.into());
+                }
+                "--audio" => {
+                    options.audio = Some(args.next().ok_or("--audio needs a file")?.into());
+                }
+                "--mute" => options.mute = true,
+                "--bench-boids" => options.bench_boids = true,
+                _ => return Err(format!("unknown argument \"{}\"", arg)),
+            }
+        }
+        if options.record.is_some() && options.replay.is_some() {
+            return Err("--record and --replay can't be used together".to_string());
+        }
+        // a replay brings its own, anything else would play back a different run
+        if options.replay.is_some() && (options.flock_config.is_some() || options.difficulty.is_some() || options.level.is_some()) {
+            return Err("--replay plays back with the flock config, difficulty and level it was recorded with".to_string());
+        }
+        Ok(options)
+    }
+}
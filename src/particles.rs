@@ -0,0 +1,178 @@
+use std::f32::consts::PI;
+
+use rand::{Rng, SeedableRng};
+use tetra::graphics::{Color, DrawParams, Texture, TextureFormat};
+use tetra::math::Vec2;
+use tetra::Context;
+
+use super::simulation::GameRng;
+
+// live particles never go past this, bursts that would are cut short
+pub const MAX_PARTICLES: usize = 1024;
+
+// how one kind of burst looks. angles are in radians with 0 pointing right and PI / 2 down,
+// speeds and gravity are per second like everything else
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Emitter {
+    pub count: usize,
+    pub lifetime: (f32, f32),
+    pub speed: (f32, f32),
+    pub direction: f32,
+    // particles leave up to this far either side of direction, PI sends them every way
+    pub spread: f32,
+    pub gravity: f32,
+    // side length in pixels
+    pub size: f32,
+    pub start_color: Color,
+    pub end_color: Color,
+}
+
+pub const TOKEN_PICKUP: Emitter = Emitter {
+    count: 24,
+    lifetime: (0.3, 0.6),
+    speed: (60.0, 180.0),
+    direction: -PI / 2.0,
+    spread: PI,
+    gravity: 300.0,
+    size: 3.0,
+    start_color: Color::rgb(1.0, 0.85, 0.2),
+    end_color: Color::rgba(1.0, 1.0, 0.6, 0.0),
+};
+
+// goes off every tick a bat is touching the player, so only a couple at a time
+pub const BAT_HIT: Emitter = Emitter {
+    count: 2,
+    lifetime: (0.2, 0.4),
+    speed: (40.0, 120.0),
+    direction: -PI / 2.0,
+    spread: PI,
+    gravity: 600.0,
+    size: 2.0,
+    start_color: Color::rgb(0.93, 0.11, 0.14),
+    end_color: Color::rgba(0.4, 0.0, 0.0, 0.0),
+};
+
+pub const PLAYER_DEATH: Emitter = Emitter {
+    count: 80,
+    lifetime: (0.6, 1.2),
+    speed: (80.0, 320.0),
+    direction: -PI / 2.0,
+    spread: PI,
+    gravity: 500.0,
+    size: 3.0,
+    start_color: Color::rgb(0.77, 0.81, 0.63),
+    end_color: Color::rgba(0.3, 0.33, 0.24, 0.0),
+};
+
+#[derive(Debug, Clone, Copy)]
+struct Particle {
+    position: Vec2<f32>,
+    prev_position: Vec2<f32>,
+    velocity: Vec2<f32>,
+    age: f32,
+    lifetime: f32,
+    gravity: f32,
+    size: f32,
+    start_color: Color,
+    end_color: Color,
+}
+
+// purely for show, so it lives outside the simulation and has its own rng: adding or tuning an
+// effect can't change how a seed plays. the pool is allocated once, live particles are kept at the
+// front and a dead one is swapped with the last live one
+pub struct Particles {
+    pool: Vec<Particle>,
+    live: usize,
+    rng: GameRng,
+    texture: Texture,
+}
+
+impl Particles {
+    pub fn new(ctx: &mut Context) -> tetra::Result<Particles> {
+        let blank = Particle {
+            position: Vec2::zero(),
+            prev_position: Vec2::zero(),
+            velocity: Vec2::zero(),
+            age: 0.0,
+            lifetime: 0.0,
+            gravity: 0.0,
+            size: 0.0,
+            start_color: Color::WHITE,
+            end_color: Color::WHITE,
+        };
+        Ok(Particles {
+            pool: vec![blank; MAX_PARTICLES],
+            live: 0,
+            rng: GameRng::from_entropy(),
+            // one white pixel, scaled and tinted for each particle
+            texture: Texture::from_data(ctx, 1, 1, TextureFormat::Rgba8, &[255, 255, 255, 255])?,
+        })
+    }
+
+    pub fn emit(&mut self, emitter: &Emitter, position: Vec2<f32>) {
+        let count = emitter.count.min(MAX_PARTICLES - self.live);
+        for _ in 0..count {
+            let angle = emitter.direction + self.rng.gen_range(-emitter.spread..=emitter.spread);
+            let speed = self.rng.gen_range(emitter.speed.0..=emitter.speed.1);
+            self.pool[self.live] = Particle {
+                position,
+                prev_position: position,
+                velocity: Vec2::new(angle.cos(), angle.sin()) * speed,
+                age: 0.0,
+                lifetime: self.rng.gen_range(emitter.lifetime.0..=emitter.lifetime.1),
+                gravity: emitter.gravity,
+                size: emitter.size,
+                start_color: emitter.start_color,
+                end_color: emitter.end_color,
+            };
+            self.live += 1;
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let mut i = 0;
+        while i < self.live {
+            let particle = &mut self.pool[i];
+            particle.age += dt;
+            if particle.age >= particle.lifetime {
+                self.live -= 1;
+                self.pool.swap(i, self.live);
+                continue;
+            }
+            particle.prev_position = particle.position;
+            particle.velocity.y += particle.gravity * dt;
+            particle.position += particle.velocity * dt;
+            i += 1;
+        }
+    }
+
+    // gone straight away, e.g. when a new run starts
+    pub fn clear(&mut self) {
+        self.live = 0;
+    }
+
+    pub fn live(&self) -> usize {
+        self.live
+    }
+
+    pub fn draw(&self, ctx: &mut Context, blend: f32) {
+        for particle in &self.pool[..self.live] {
+            let t = particle.age / particle.lifetime;
+            let (from, to) = (particle.start_color, particle.end_color);
+            let color = Color::rgba(
+                from.r + (to.r - from.r) * t,
+                from.g + (to.g - from.g) * t,
+                from.b + (to.b - from.b) * t,
+                from.a + (to.a - from.a) * t,
+            );
+            self.texture.draw(
+                ctx,
+                DrawParams::new()
+                    .position(Vec2::lerp(particle.prev_position, particle.position, blend))
+                    .origin(Vec2::new(0.5, 0.5))
+                    .scale(Vec2::new(particle.size, particle.size))
+                    .color(color),
+            );
+        }
+    }
+}
@@ -1,58 +1,67 @@
-use tetra::graphics::{Texture};
+use tetra::graphics::{DrawParams, Texture};
 use tetra::math::Vec2;
 use tetra::Context;
-use rand::rngs::ThreadRng;
-use rand::{self, Rng};
 
+use super::collision::Aabb;
+use super::game_object::{GameObject, ObjectKind, Textures, World};
+use super::simulation::{GameEvent, GameRng};
+
+
+// what a platform looks like
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlatformStyle {
+    // random layout, the sprite is drawn at its own size
+    Generated,
+    // tiles from a level map, the sprite is stretched over the tile
+    Tile,
+    Brick,
+}
 
 #[derive(Debug, Clone)]
 pub struct Platform {
-    texture: Texture,
     position: Vec2<f32>,
     dimension: Vec2<f32>,
-    rng: ThreadRng
+    style: PlatformStyle
 }
 
 impl Platform {
-    pub fn new(t: Texture, dim: Vec2<f32>, use_random: bool, pos: Vec2<f32>) -> Platform {
-        let mut rng = rand::thread_rng();
-        if use_random {
-            let x_pos = ((rng.gen::<f32>() * 9.41).floor()) * 68.0;
-            let y_pos = ((rng.gen::<f32>() * 12.97).floor()) * 37.0 + 37.0;
-            Platform {        
-                texture: t,
-                position: Vec2::new(x_pos,y_pos),
-                dimension: dim,     
-                rng  
-            }
-        } else {
-            Platform {
-                texture: t,
-                position: pos,
-                dimension: dim,
-                rng
-            }
+    pub fn new(dim: Vec2<f32>, pos: Vec2<f32>, style: PlatformStyle) -> Platform {
+        Platform {
+            position: pos,
+            dimension: dim,
+            style,
         }
     }
 
-    pub fn update(&mut self, down_speed: f32) {
-        self.position.y += down_speed;
-        if self.position.y >= super::WINDOW_HEIGHT as f32 {
-            self.position.y -= super::WINDOW_HEIGHT as f32 + (1.0 * 37.0);
-            self.position.x = ((self.rng.gen::<f32>() * 9.41).floor()) * 68.0;
+    // back into play somewhere new, see PlatformGenerator
+    pub fn respawn(&mut self, pos: Vec2<f32>) {
+        self.position = pos;
+    }
 
-        }
+    fn draw_stretched(&self, ctx: &mut Context, texture: &Texture, position: Vec2<f32>) {
+        let scale = self.dimension / Vec2::new(texture.width() as f32, texture.height() as f32);
+        texture.draw(ctx, DrawParams::new().position(position).scale(scale));
     }
+}
 
-    pub fn draw(&self, ctx: &mut Context) {
-        self.texture.draw(ctx, self.position);
+impl GameObject for Platform {
+    fn kind(&self) -> ObjectKind {
+        ObjectKind::Platform
     }
 
-    pub fn get_pos(&self) -> Vec2<f32> {
-        self.position
+    fn bounds(&self) -> Aabb {
+        Aabb::new(self.position, self.dimension)
     }
 
-    pub fn get_dim(&self) -> Vec2<f32> {
-        self.dimension
+    // platforms stay put, once the view has left one behind the simulation finds it a new place above
+    fn update(&mut self, _world: &World, _rng: &mut GameRng, _events: &mut Vec<GameEvent>) {}
+
+    fn draw(&self, ctx: &mut Context, textures: &Textures, _blend: f32) {
+        let position = self.position;
+        match self.style {
+            PlatformStyle::Generated => textures.platform.draw(ctx, position),
+            PlatformStyle::Tile => self.draw_stretched(ctx, &textures.platform, position),
+            PlatformStyle::Brick => self.draw_stretched(ctx, &textures.brick, position),
+        }
     }
-}
\ No newline at end of file
+}
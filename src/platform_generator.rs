@@ -0,0 +1,252 @@
+use rand::Rng;
+use tetra::math::Vec2;
+
+use super::collision::Aabb;
+use super::simulation::GameRng;
+use super::WINDOW_WIDTH;
+
+// only count on this much of the real jump, so a reachable platform doesn't need a perfect one
+const REACH_MARGIN: f32 = 0.8;
+// room left around every platform, enough for the player to fit through between two
+const PLATFORM_GAP: f32 = 12.0;
+// random placements tried before giving up on a platform for this tick
+const ATTEMPTS: usize = 32;
+// new platforms come in somewhere in this band above the top of the view
+const SPAWN_BAND: f32 = 37.0;
+
+// how far the player can get in one jump, worked out from the same numbers the physics uses
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct JumpEnvelope {
+    pub jump_speed: f32,
+    pub gravity: f32,
+    // horizontal speed approaches top_speed by a factor of e every 1 / friction seconds
+    pub top_speed: f32,
+    pub friction: f32,
+}
+
+impl JumpEnvelope {
+    // highest a jump gets the player's feet above where it started
+    pub fn max_height(&self) -> f32 {
+        self.jump_speed * self.jump_speed / (2.0 * self.gravity)
+    }
+
+    // how far sideways a jump from standing still gets before coming down on something rise
+    // pixels higher (negative for lower), None if it's too high to land on
+    pub fn reach(&self, rise: f32) -> Option<f32> {
+        let discriminant = self.jump_speed * self.jump_speed - 2.0 * self.gravity * rise;
+        if discriminant < 0.0 {
+            return None;
+        }
+        // time until the feet come back down through that height
+        let t = (self.jump_speed + discriminant.sqrt()) / self.gravity;
+        Some(self.top_speed * (t - (1.0 - (-self.friction * t).exp()) / self.friction))
+    }
+
+    // whether a player standing on from can jump or drop onto to
+    pub fn can_reach(&self, from: &Aabb, to: &Aabb) -> bool {
+        let rise = from.min().y - to.min().y;
+        if rise > self.max_height() * REACH_MARGIN {
+            return false;
+        }
+        let gap = (to.min().x - from.max().x).max(from.min().x - to.max().x).max(0.0);
+        self.reach(rise).is_some_and(|reach| gap <= reach * REACH_MARGIN)
+    }
+}
+
+// places platforms so they never overlap and every one can be reached from one already there
+pub struct PlatformGenerator {
+    envelope: JumpEnvelope,
+    size: Vec2<f32>,
+}
+
+impl PlatformGenerator {
+    pub fn new(envelope: JumpEnvelope, size: Vec2<f32>) -> PlatformGenerator {
+        PlatformGenerator { envelope, size }
+    }
+
+    // somewhere between min_y and max_y for the top of a new platform, None if nowhere tried works.
+    // keep_clear is space that has to stay empty without being something to jump from
+    pub fn place(&self, existing: &[Aabb], keep_clear: &[Aabb], min_y: f32, max_y: f32, view: &Aabb, rng: &mut GameRng) -> Option<Vec2<f32>> {
+        let max_x = WINDOW_WIDTH as f32 - self.size.x;
+        for _ in 0..ATTEMPTS {
+            let candidate = Aabb::new(
+                Vec2::new(rng.gen::<f32>() * max_x, min_y + rng.gen::<f32>() * (max_y - min_y)),
+                self.size,
+            );
+            let blocked = keep_clear.iter().any(|area| candidate.expanded(PLATFORM_GAP).overlaps(area));
+            if !blocked && self.fits(existing, &candidate, view) {
+                return Some(candidate.position);
+            }
+        }
+        None
+    }
+
+    // a new platform coming in above the top of the view
+    pub fn spawn(&self, existing: &[Aabb], view: &Aabb, rng: &mut GameRng) -> Option<Vec2<f32>> {
+        let top = view.min().y - self.size.y;
+        self.place(existing, &[], top - SPAWN_BAND, top, view, rng)
+    }
+
+    // platforms the view has left below are on their way to being respawned, so they don't count
+    pub fn in_play(platform: &Aabb, view: &Aabb) -> bool {
+        platform.min().y < view.max().y
+    }
+
+    fn fits(&self, existing: &[Aabb], candidate: &Aabb, view: &Aabb) -> bool {
+        let padded = candidate.expanded(PLATFORM_GAP);
+        let on_screen = existing.iter().filter(|platform| PlatformGenerator::in_play(platform, view));
+        let mut reachable = false;
+        for platform in on_screen {
+            if padded.overlaps(platform) {
+                return false;
+            }
+            reachable |= self.envelope.can_reach(platform, candidate);
+        }
+        reachable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use tetra::math::Vec2;
+
+    use super::{JumpEnvelope, PlatformGenerator, PLATFORM_GAP};
+    use crate::collision::Aabb;
+    use crate::difficulty::Difficulty;
+    use crate::flock_config::FlockConfig;
+    use crate::game_object::GameObject;
+    use crate::player::Player;
+    use crate::simulation::{GameRng, Simulation};
+
+    const SCROLL: f32 = 6.0;
+
+    fn assert_spaced_out(platforms: &[Aabb], view: &Aabb, seed: u64) {
+        let in_play: Vec<&Aabb> = platforms.iter().filter(|p| PlatformGenerator::in_play(p, view)).collect();
+        for (i, a) in in_play.iter().enumerate() {
+            for b in &in_play[i + 1..] {
+                assert!(!a.expanded(PLATFORM_GAP).overlaps(b), "seed {}: {:?} is too close to {:?}", seed, a, b);
+            }
+        }
+        assert!(!in_play.is_empty(), "seed {}: nothing left to stand on", seed);
+    }
+
+    // the layout Simulation::new builds, then the view climbing for long enough to respawn
+    // every platform many times over
+    #[test]
+    fn every_platform_stays_reachable() {
+        let envelope = Player::jump_envelope();
+        for seed in 0..4 {
+            let simulation = Simulation::new(seed, FlockConfig::default(), Difficulty::constant(0), 60.0, None);
+            let generator = PlatformGenerator::new(envelope, simulation.platforms()[0].bounds().size);
+            let mut view = simulation.camera().view();
+            let mut platforms: Vec<Aabb> = simulation.platforms().iter().map(GameObject::bounds).collect();
+
+            // each platform in the starting layout can be got to from one placed before it
+            for (i, platform) in platforms.iter().enumerate().skip(1) {
+                if PlatformGenerator::in_play(platform, &view) {
+                    assert!(
+                        platforms[..i].iter().any(|from| PlatformGenerator::in_play(from, &view) && envelope.can_reach(from, platform)),
+                        "seed {}: starting platform {} can't be reached",
+                        seed,
+                        i
+                    );
+                }
+            }
+            assert_spaced_out(&platforms, &view, seed);
+
+            let mut rng = GameRng::seed_from_u64(seed);
+            let mut spawned = 0;
+            while spawned < 3000 {
+                view = view.translated(Vec2::new(0.0, -SCROLL));
+                for i in 0..platforms.len() {
+                    if PlatformGenerator::in_play(&platforms[i], &view) {
+                        continue;
+                    }
+                    let Some(position) = generator.spawn(&platforms, &view, &mut rng) else {
+                        continue;
+                    };
+                    let placed = Aabb::new(position, platforms[i].size);
+                    assert!(
+                        platforms.iter().any(|from| PlatformGenerator::in_play(from, &view) && envelope.can_reach(from, &placed)),
+                        "seed {}: platform at {:?} can't be reached",
+                        seed,
+                        position
+                    );
+                    platforms[i] = placed;
+                    spawned += 1;
+                }
+                assert_spaced_out(&platforms, &view, seed);
+            }
+        }
+    }
+
+    // steps a jump from standing still the way the player does, at a much finer dt
+    fn simulate_jump(envelope: &JumpEnvelope, rise: f32) -> Option<f32> {
+        let dt = 1e-5;
+        let (mut x, mut y) = (0.0f32, 0.0f32);
+        let (mut vx, mut vy) = (0.0f32, -envelope.jump_speed);
+        let mut highest = 0.0f32;
+        loop {
+            vy += envelope.gravity * dt;
+            vx = envelope.top_speed + (vx - envelope.top_speed) * (-envelope.friction * dt).exp();
+            x += vx * dt;
+            y += vy * dt;
+            highest = highest.max(-y);
+            // on the way down through the height of the landing
+            if vy > 0.0 && -y <= rise {
+                return if highest >= rise { Some(x) } else { None };
+            }
+        }
+    }
+
+    #[test]
+    fn max_height_matches_the_player() {
+        let envelope = Player::jump_envelope();
+        let mut highest = 0.0f32;
+        let (mut y, mut vy) = (0.0f32, -envelope.jump_speed);
+        let dt = 1e-5;
+        while vy < 0.0 {
+            vy += envelope.gravity * dt;
+            y += vy * dt;
+            highest = highest.max(-y);
+        }
+        assert!((envelope.max_height() - highest).abs() < 0.1, "{} vs {}", envelope.max_height(), highest);
+    }
+
+    #[test]
+    fn reach_matches_the_player() {
+        let envelope = Player::jump_envelope();
+        let max_height = envelope.max_height();
+        let mut last = f32::INFINITY;
+        for rise in [-200.0, -50.0, 0.0, 40.0, 100.0, max_height * 0.9, max_height * 0.99] {
+            let reach = envelope.reach(rise).unwrap();
+            let simulated = simulate_jump(&envelope, rise).unwrap();
+            assert!((reach - simulated).abs() < 0.5, "rise {}: {} vs {}", rise, reach, simulated);
+            // the higher the landing the less time there is to get across
+            assert!(reach < last, "rise {}", rise);
+            last = reach;
+        }
+        assert_eq!(envelope.reach(max_height + 1.0), None);
+        assert_eq!(simulate_jump(&envelope, max_height + 1.0), None);
+    }
+
+    #[test]
+    fn can_reach_keeps_a_margin() {
+        let envelope = Player::jump_envelope();
+        let size = Vec2::new(78.0, 50.0);
+        let from = Aabb::new(Vec2::new(100.0, 300.0), size);
+        let beside = |gap: f32, rise: f32| Aabb::new(Vec2::new(from.max().x + gap, from.min().y - rise), size);
+
+        assert!(envelope.can_reach(&from, &beside(0.0, 0.0)));
+        assert!(envelope.can_reach(&from, &beside(0.0, envelope.max_height() * 0.75)));
+        assert!(!envelope.can_reach(&from, &beside(0.0, envelope.max_height() * 0.85)));
+
+        let reach = envelope.reach(50.0).unwrap();
+        assert!(envelope.can_reach(&from, &beside(reach * 0.75, 50.0)));
+        assert!(!envelope.can_reach(&from, &beside(reach * 0.85, 50.0)));
+        // the same gap to the left
+        let left = Aabb::new(Vec2::new(from.min().x - reach * 0.75 - size.x, from.min().y - 50.0), size);
+        assert!(envelope.can_reach(&from, &left));
+    }
+}
@@ -1,39 +1,106 @@
 use tetra::math::Vec2;
-use tetra::graphics::{DrawParams, Texture, Color, self};
-use tetra::graphics::text::{Font, Text};
-use tetra::input::{self, Key};
 use tetra::Context;
 
-use crate::WINDOW_HEIGHT;
+use super::animation::{Animation, Frame};
+use super::queue::Queue;
+use super::collision::Aabb;
+use super::game_object::{GameObject, ObjectKind, Textures, World};
+use super::platform_generator::JumpEnvelope;
+use super::simulation::{GameEvent, GameRng, InputFrame};
+
+// everything below is per second so the feel doesn't change with the tick rate
+const GRAVITY: f32 = 2700.0;
+const FAST_FALL: f32 = 1440.0;
+const JUMP_SPEED: f32 = 900.0;
+const RUN_ACCELERATION: f32 = 4820.0;
+// horizontal speed decays by a factor of e every 1 / FRICTION seconds
+const FRICTION: f32 = 13.39;
+// while touching a bat
+const BAT_DAMAGE: f32 = 12.0;
+const FALL_DAMAGE: f32 = 150.0;
+// a move can stop against at most a floor, a wall and a corner
+const MAX_SLIDES: usize = 3;
+// slower than this on the ground and the player is standing still
+const RUN_THRESHOLD: f32 = 20.0;
+
+// indices into player-sheet.png, the sheet faces right and gets mirrored for left
+static IDLE: [Frame; 2] = [Frame { index: 0, duration: 0.6 }, Frame { index: 1, duration: 0.4 }];
+static RUN: [Frame; 4] = [
+    Frame { index: 2, duration: 0.09 },
+    Frame { index: 3, duration: 0.09 },
+    Frame { index: 4, duration: 0.09 },
+    Frame { index: 5, duration: 0.09 },
+];
+static JUMP: [Frame; 1] = [Frame { index: 6, duration: 1.0 }];
+static FALL: [Frame; 1] = [Frame { index: 7, duration: 1.0 }];
+static WALL_SLIDE: [Frame; 1] = [Frame { index: 8, duration: 1.0 }];
+static HURT: [Frame; 2] = [Frame { index: 9, duration: 0.1 }, Frame { index: 10, duration: 0.1 }];
+static DEAD: [Frame; 3] = [
+    Frame { index: 11, duration: 0.15 },
+    Frame { index: 12, duration: 0.15 },
+    Frame { index: 13, duration: 1.0 },
+];
+
+// what the sprite is showing, worked out from the physics at the end of every tick
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerAnim {
+    Idle,
+    Run,
+    Jump,
+    Fall,
+    WallSlide,
+    Hurt,
+    Dead,
+}
 
-use super::{queue::Queue, platform::Platform, enemy::Enemy, token::Token};
+impl PlayerAnim {
+    // frames and whether they loop
+    fn clip(self) -> (&'static [Frame], bool) {
+        match self {
+            PlayerAnim::Idle => (&IDLE, true),
+            PlayerAnim::Run => (&RUN, true),
+            PlayerAnim::Jump => (&JUMP, false),
+            PlayerAnim::Fall => (&FALL, false),
+            PlayerAnim::WallSlide => (&WALL_SLIDE, false),
+            PlayerAnim::Hurt => (&HURT, false),
+            PlayerAnim::Dead => (&DEAD, false),
+        }
+    }
+}
 
 pub struct Player {
-    textures: Vec<Texture>,
     position: Vec2<f32>,
-    spawn_pos: Vec2<f32>,
+    prev_position: Vec2<f32>,
     dimension: Vec2<f32>,
     velocity: Vec2<f32>,
+    // relative to the top left of the view, so standing still on screen while it scrolls
+    // leaves a trail the bats can lose
     pos_buf: Queue<Vec2<f32>>,
     health: f32,
     is_facing_left: bool,
     is_jumping: bool,
     is_wall_jumping: bool,
     collision: bool,
+    // what got touched this tick, for picking the animation
+    on_ground: bool,
+    on_wall: bool,
+    was_hurt: bool,
+    anim: PlayerAnim,
+    animation: Animation,
     move_speed: f32,
-    restart_text: Text,
     score: u32
 }
 
 impl Player {
-    pub fn new(txts: Vec<Texture>, start_pos: Vec2<f32>, restart_text: Text) -> Player {
-
-        let mut q = Queue::new(super::POSITION_BUFFER_SIZE);
+    pub fn new(start_pos: Vec2<f32>, tick_rate: f64) -> Player {
+        // the bats chase where the player was PLAYER_TRAIL_SECONDS ago
+        let trail_len = ((super::PLAYER_TRAIL_SECONDS as f64 * tick_rate).round() as usize).max(1);
+        let mut q = Queue::new(trail_len);
+        // a run starts with the view at the origin, so this is where it is on screen too
         q.fill_with(start_pos);
         Player {
-            textures: txts,
             position: start_pos,
-            spawn_pos: start_pos,
+            prev_position: start_pos,
             dimension: Vec2::new(10.0, 10.0),
             velocity: Vec2::new(0.0,0.0),
             pos_buf: q,
@@ -42,256 +109,343 @@ impl Player {
             is_jumping: false,
             is_wall_jumping: false,
             collision: false,
-            move_speed: 1.2,
-            restart_text,
+            on_ground: false,
+            on_wall: false,
+            was_hurt: false,
+            anim: PlayerAnim::Idle,
+            animation: Animation::new(&IDLE, true),
+            move_speed: RUN_ACCELERATION,
             score: 0
         }
     }
 
-    pub fn oldest_player_pos(&self) -> Vec2<f32> {
-        if 4 <= super::POSITION_BUFFER_SIZE - 1 {
-            if let Some(&oldest_player_pos) = self.pos_buf.get_at(4) {
-                oldest_player_pos
-            } else {
-                Vec2::new(0.0,0.0)
-            }
-        } else {
-            Vec2::new(0.0,0.0)
+    // what the platform generator has to work with
+    pub fn jump_envelope() -> JumpEnvelope {
+        JumpEnvelope {
+            jump_speed: JUMP_SPEED,
+            gravity: GRAVITY,
+            top_speed: RUN_ACCELERATION / FRICTION,
+            friction: FRICTION,
         }
     }
 
-    fn platform_collisions(&mut self, platforms: Vec<Platform>) {
-        for platform in &platforms {
-            let pos = platform.get_pos();
-            let dim = platform.get_dim();
-            let fut_x = self.position.x + self.velocity.x;
-            if  fut_x >= pos.x + dim.x {
-                
-            } else if fut_x + self.dimension.x <= pos.x {
-                
-            } else if self.position.y >= pos.y + dim.y {
-                
-            } else if self.position.y + self.dimension.y <= pos.y {
-                
-            } else {
-                self.velocity.x = 0.0;
-                if !self.is_wall_jumping {
-                    self.is_wall_jumping = true;
-                    self.is_jumping = false;
-                }
-                    
+    // where on screen the player was at the back of the trail, put back into the world at the current view
+    pub fn oldest_player_pos(&self, view: &Aabb) -> Vec2<f32> {
+        self.pos_buf.peek().map(|&on_screen| on_screen + view.min()).unwrap_or(self.position)
+    }
+
+    // react to touching a platform on the side facing normal
+    fn platform_contact(&mut self, normal: Vec2<f32>) {
+        if normal.x != 0.0 {
+            self.velocity.x = 0.0;
+            self.on_wall = true;
+            if !self.is_wall_jumping {
+                self.is_wall_jumping = true;
+                self.is_jumping = false;
             }
-            
-            let fut_y = self.position.y + self.velocity.y;
-            if  self.position.x >= pos.x + dim.x {
-                
-            } else if self.position.x + self.dimension.x <= pos.x {
-                
-            } else if fut_y >= pos.y + dim.y {
-                
-            } else if fut_y + self.dimension.y <= pos.y {
-                
+        }
+        if normal.y != 0.0 {
+            self.velocity.y = 0.0;
+            self.collision = true;
+            if normal.y > 0.0 {
+                // bumped our head
+                self.is_jumping = true;
             } else {
-                self.velocity.y = 0.0;
-                self.collision = true;
-                if self.position.y > pos.y {
-                    self.is_jumping = true;
-                } else {
-                    self.is_jumping = false;
-                    self.position.y -= 0.1;
-                    self.is_wall_jumping = false;
-                }
+                self.is_jumping = false;
+                self.is_wall_jumping = false;
+                self.on_ground = true;
             }
         }
     }
 
-    fn enemy_collisions(&mut self, enemies: Vec<Enemy>) {
-        for enemy in &enemies {
-            // formula for finding mean without some sample value, in this case, the current enemy boid pos
-            // let center_minus_curr = ((n as f32 * center) - enemy.get_pos()) /  ((n - 1) as f32);
-            let e_pos = enemy.get_pos();
-            let e_dim = enemy.get_dim();
-            // player-bat collision detection
-            let fut_x = self.position.x + self.velocity.x;
-            if  fut_x >= e_pos.x + e_dim.x {
-                
-            } else if fut_x + self.dimension.x <= e_pos.x {
-                
-            } else if self.position.y >= e_pos.y + e_dim.y {
-                
-            } else if self.position.y + self.dimension.y <= e_pos.y {
-                
-            } else {
-                if self.health > 0.0 {
-                    self.health -= 0.1;
-                }
+    // moves the player by velocity * dt, stopping at and sliding along platforms instead of passing through them
+    fn platform_collisions(&mut self, platforms: &[Aabb], dt: f32) {
+        // platforms scroll, so one may have moved into us since last tick
+        for platform in platforms {
+            if let Some(push) = self.bounds().penetration(platform) {
+                self.position += push;
+                self.platform_contact(push / push.magnitude());
             }
-            
-            let fut_y = self.position.y + self.velocity.y;
-            if  self.position.x >= e_pos.x + e_dim.x {
-                
-            } else if self.position.x + self.dimension.x <= e_pos.x {
-
-            } else if fut_y >= e_pos.y + e_dim.y {
-                
-            } else if fut_y + self.dimension.y <= e_pos.y {
-                
+        }
+
+        let mut remaining = self.velocity * dt;
+        for _ in 0..MAX_SLIDES {
+            let bounds = self.bounds();
+            let hit = platforms
+                .iter()
+                .filter_map(|platform| bounds.sweep(remaining, platform))
+                .min_by(|a, b| a.time.total_cmp(&b.time));
+
+            let Some(hit) = hit else {
+                self.position += remaining;
+                return;
+            };
+            self.position += remaining * hit.time;
+            self.platform_contact(hit.normal);
+            // carry on along the surface with whatever movement is left
+            remaining *= 1.0 - hit.time;
+            if hit.normal.x != 0.0 {
+                remaining.x = 0.0;
             } else {
-                if self.health > 0.0 {
-                    self.health -= 0.1;
-                }
+                remaining.y = 0.0;
             }
-        }            
+        }
     }
 
-    fn token_collisions(&mut self, tokens: Vec<Token>) {
-        for token in &tokens {
-            // formula for finding mean without some sample value, in this case, the current enemy boid pos
-            // let center_minus_curr = ((n as f32 * center) - enemy.get_pos()) /  ((n - 1) as f32);
-            let e_pos = token.get_pos();
-            let e_dim = token.get_dim();
-
-            let mut hit_y = false;
-            let mut hit_x = false;
-            // player-bat collision detection
-            let fut_x = self.position.x + self.velocity.x;
-            if  fut_x >= e_pos.x + e_dim.x {
-                
-            } else if fut_x + self.dimension.x <= e_pos.x {
-                
-            } else if self.position.y >= e_pos.y + e_dim.y {
-                
-            } else if self.position.y + self.dimension.y <= e_pos.y {
-                
-            } else {
-                hit_x = true;
-            }
-            
-            let fut_y = self.position.y + self.velocity.y;
-            if  self.position.x >= e_pos.x + e_dim.x {
-                
-            } else if self.position.x + self.dimension.x <= e_pos.x {
-
-            } else if fut_y >= e_pos.y + e_dim.y {
-                
-            } else if fut_y + self.dimension.y <= e_pos.y {
-                
-            } else {
-                hit_y = true;
+    fn enemy_collisions(&mut self, start: &Aabb, moved: Vec2<f32>, enemies: &[Aabb], dt: f32, events: &mut Vec<GameEvent>) {
+        for enemy in enemies {
+            if start.touches_during(moved, enemy) && self.health > 0.0 {
+                self.health = (self.health - BAT_DAMAGE * dt).max(0.0);
+                self.was_hurt = true;
             }
+        }
+        // once a tick however many bats there are
+        if self.was_hurt {
+            events.push(GameEvent::PlayerHurt { position: self.position });
+        }
+    }
 
-            if hit_x || hit_y {
-                if self.health > 0.0 {
-                    self.health += 10.0;
-                    self.score += 1;
-                }
-                if self.health > 100.0 {
-                    self.health = 100.0
-                }
+    // a token is used up when it's touched, so this reports which ones were
+    fn token_collisions(&mut self, start: &Aabb, moved: Vec2<f32>, tokens: &[Aabb], events: &mut Vec<GameEvent>) {
+        for (index, token) in tokens.iter().enumerate() {
+            if start.touches_during(moved, token) && self.health > 0.0 {
+                self.health = (self.health + 10.0).min(100.0);
+                self.score += 1;
+                events.push(GameEvent::TokenCollected { index, position: token.center() });
             }
-        }            
+        }
     }
 
-    pub fn update(&mut self, ctx: &mut Context, platforms: Vec<Platform>, enemies: Vec<Enemy>, tokens: Vec<Token>) {
+    // hurt plays through at least once before anything but dying can take over
+    fn next_anim(&self) -> PlayerAnim {
+        if self.is_dead() {
+            PlayerAnim::Dead
+        } else if self.was_hurt || (self.anim == PlayerAnim::Hurt && !self.animation.is_finished()) {
+            PlayerAnim::Hurt
+        } else if self.on_ground {
+            if self.velocity.x.abs() > RUN_THRESHOLD {
+                PlayerAnim::Run
+            } else {
+                PlayerAnim::Idle
+            }
+        } else if self.on_wall && self.velocity.y > 0.0 {
+            PlayerAnim::WallSlide
+        } else if self.velocity.y < 0.0 {
+            PlayerAnim::Jump
+        } else {
+            PlayerAnim::Fall
+        }
+    }
 
-        if self.position.y < WINDOW_HEIGHT as f32 + self.dimension.y {
-            self.velocity.y += 0.75;
+    fn animate(&mut self, dt: f32) {
+        let next = self.next_anim();
+        let (frames, looping) = next.clip();
+        if next == PlayerAnim::Hurt && self.was_hurt && self.animation.is_finished() {
+            // still touching a bat, flash again
+            self.animation = Animation::new(frames, looping);
         } else {
-            if self.health > 0.0 {
-                self.health -= 2.5;
-            }
+            self.animation.play(frames, looping);
         }
-        self.velocity.x *= 0.8;
+        self.anim = next;
+
+        // legs keep up with the ground speed
+        let rate = match next {
+            PlayerAnim::Run => (self.velocity.x.abs() * FRICTION / RUN_ACCELERATION).clamp(0.5, 1.5),
+            _ => 1.0,
+        };
+        self.animation.update(dt * rate);
+    }
 
-        if self.position.y + self.velocity.y < 0.0 {
-            self.velocity.y = 0.0;
+    pub fn get_anim(&self) -> PlayerAnim {
+        self.anim
+    }
+
+    pub fn get_health(&self) -> f32 {
+        self.health
+    }
+
+    pub fn get_score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health <= 0.0
+    }
+}
+
+impl GameObject for Player {
+    fn kind(&self) -> ObjectKind {
+        ObjectKind::Player
+    }
+
+    fn bounds(&self) -> Aabb {
+        Aabb::new(self.position, self.dimension)
+    }
+
+    fn update(&mut self, world: &World, _rng: &mut GameRng, events: &mut Vec<GameEvent>) {
+        let was_dead = self.is_dead();
+        // a dead player is just a body, whatever is being pressed
+        let idle = InputFrame::default();
+        let inputs = if was_dead { &idle } else { world.inputs };
+        let dt = world.dt;
+        self.prev_position = self.position;
+        let was_on_ground = self.on_ground;
+        self.on_ground = false;
+        self.on_wall = false;
+        self.was_hurt = false;
+
+        // dropped out of the bottom of the view
+        if self.position.y < world.view.max().y + self.dimension.y {
+            self.velocity.y += GRAVITY * dt;
+        } else if self.health > 0.0 {
+            self.health = (self.health - FALL_DAMAGE * dt).max(0.0);
         }
-        
-        if input::is_key_down(ctx, Key::A) || input::is_key_down(ctx, Key::Left) {
-            self.velocity.x -= self.move_speed;
-            self.is_facing_left = true;
+
+        if self.position.y + self.velocity.y * dt < world.view.min().y {
+            self.velocity.y = 0.0;
         }
 
-        if input::is_key_down(ctx, Key::D) || input::is_key_down(ctx, Key::Right) {
-            self.velocity.x += self.move_speed;
+        let run = self.move_speed * inputs.run_amount();
+        if inputs.run < 0 {
+            self.is_facing_left = true;
+        } else if inputs.run > 0 {
             self.is_facing_left = false;
-
         }
 
-        if !self.is_jumping && (input::is_key_pressed(ctx, Key::W) || input::is_key_pressed(ctx, Key::Up)) {
-            self.velocity.y -= 15.0;
-            self.is_jumping = true;
-        }
+        // solved exactly for a constant push against friction, so top speed is the same at any tick rate
+        let top_speed = run / FRICTION;
+        self.velocity.x = top_speed + (self.velocity.x - top_speed) * (-FRICTION * dt).exp();
 
-        if input::is_key_down(ctx, Key::S) || input::is_key_down(ctx, Key::Down) {
-            self.velocity.y += 0.4;
+        if !self.is_jumping && inputs.jump {
+            self.velocity.y -= JUMP_SPEED;
+            self.is_jumping = true;
+            events.push(GameEvent::Jumped { position: self.position });
         }
 
-        if input::is_key_pressed(ctx, Key::R) {
-            self.health = 100.0;
-            self.position = self.spawn_pos;
+        if inputs.fast_fall {
+            self.velocity.y += FAST_FALL * dt;
         }
 
-        // Platform Collision Check Here?
-        self.platform_collisions(platforms);
-        self.enemy_collisions(enemies);
-        self.token_collisions(tokens);
+        // the oldest position drops off the back once the trail is full
+        self.pos_buf.push_overwrite(self.position - world.view.min());
 
-        self.pos_buf.push(self.position);
-        if self.pos_buf.get_items_in_q() > super::POSITION_BUFFER_SIZE - 1 {
-            self.pos_buf.pop();
+        let start = self.bounds();
+        self.platform_collisions(world.platforms, dt);
+        if self.on_ground && !was_on_ground {
+            events.push(GameEvent::Landed { position: self.position });
+        }
+        let moved = self.position - start.position;
+        self.enemy_collisions(&start, moved, world.enemies, dt, events);
+        self.token_collisions(&start, moved, world.tokens, events);
+        if self.is_dead() && !was_dead {
+            events.push(GameEvent::PlayerDied { position: self.position });
         }
+        self.animate(dt);
+    }
 
-        self.position.y += self.velocity.y;
-        self.position.x += self.velocity.x;
+    fn draw(&self, ctx: &mut Context, textures: &Textures, blend: f32) {
+        let position = Vec2::lerp(self.prev_position, self.position, blend);
+        textures.player.draw(ctx, self.animation.index(), position, 2.0, self.is_facing_left);
+    }
+}
 
-        // println!("Player Health: {}", self.health);
+#[cfg(test)]
+mod tests {
+    use crate::difficulty::Difficulty;
+    use crate::flock_config::FlockConfig;
+    use crate::input_source::{InputSource, ScriptedInput};
+    use crate::simulation::{GameEvent, InputFrame, Simulation};
+
+    // no bats, so nothing but the input moves the player
+    fn simulation() -> Simulation {
+        Simulation::new(1, FlockConfig::default(), Difficulty::constant(0), 60.0, None)
     }
 
-    pub fn draw(&mut self, ctx: &mut Context) {
-
-        // LEFT RIGHT PLAYER TEXTURES
-        if self.health > 0.0 {
-            if self.is_facing_left {
-                self.textures[0].draw(
-                    ctx,
-                    DrawParams::new()
-                        .position(self.position)
-                        .origin(Vec2::new(8.0, 8.0))
-                        .scale(Vec2::new(2.0, 2.0)),
-                );
-            } else {
-                self.textures[1].draw(
-                    ctx,
-                    DrawParams::new()
-                        .position(self.position)
-                        .origin(Vec2::new(8.0, 8.0))
-                        .scale(Vec2::new(2.0, 2.0)),
-                );
-            }
-        } else {
-            graphics::clear(ctx, Color::rgb(0.0, 0.0, 0.0));
-            self.restart_text.draw(ctx, Vec2::new(super::WINDOW_WIDTH as f32 /2.0 - 90.0, super::WINDOW_HEIGHT as f32 / 2.0 - 48.0))
+    // every event from the run
+    fn play(simulation: &mut Simulation, input: &mut ScriptedInput) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        while !input.is_finished() {
+            simulation.step(&input.next_frame());
+            events.extend_from_slice(simulation.events());
         }
+        events
+    }
 
-        // HEALTH BAR
-        self.textures[2].draw(
-            ctx,
-            DrawParams::new()
-                .position(Vec2::new(-6.4 * (100.0 - self.health),0.0))
-                .origin(Vec2::new(0.0,0.0))
-                .scale(Vec2::new(1.0, 1.0))
-        );
+    fn jumps(events: &[GameEvent]) -> usize {
+        events.iter().filter(|event| matches!(event, GameEvent::Jumped { .. })).count()
     }
-}
 
+    fn hold(frame: InputFrame, ticks: usize) -> ScriptedInput {
+        ScriptedInput::new(Vec::new()).hold(frame, ticks)
+    }
+
+    // long enough to drop onto the first platform
+    fn settle(simulation: &mut Simulation) {
+        play(simulation, &mut hold(InputFrame::default(), 60));
+        assert!(simulation.player().on_ground);
+    }
 
-/* Platform Collision
+    #[test]
+    fn holding_a_direction_runs_that_way() {
+        let mut simulation = simulation();
+        settle(&mut simulation);
 
+        let start = simulation.player().position.x;
+        play(&mut simulation, &mut hold(InputFrame { run: InputFrame::RUN_MAX, ..Default::default() }, 20));
+        let right = simulation.player().position.x;
+        assert!(right > start + 20.0, "{} -> {}", start, right);
+        assert!(!simulation.player().is_facing_left);
 
- */
+        play(&mut simulation, &mut hold(InputFrame { run: -InputFrame::RUN_MAX, ..Default::default() }, 30));
+        assert!(simulation.player().position.x < right);
+        assert!(simulation.player().is_facing_left);
+    }
 
-/* enemy collision
+    #[test]
+    fn half_a_stick_runs_slower() {
+        let mut full = simulation();
+        let mut half = simulation();
+        settle(&mut full);
+        settle(&mut half);
+        let start = full.player().position.x;
+        play(&mut full, &mut hold(InputFrame { run: InputFrame::RUN_MAX, ..Default::default() }, 10));
+        play(&mut half, &mut hold(InputFrame { run: InputFrame::RUN_MAX / 2, ..Default::default() }, 10));
+        assert!(half.player().position.x > start);
+        assert!(half.player().position.x < full.player().position.x);
+    }
 
+    #[test]
+    fn jump_only_fires_from_the_ground() {
+        let mut simulation = simulation();
+        settle(&mut simulation);
+
+        let jump = InputFrame { jump: true, ..Default::default() };
+        let events = play(&mut simulation, &mut hold(jump, 1));
+        assert_eq!(jumps(&events), 1);
+        assert!(simulation.player().velocity.y < 0.0);
+
+        // still going up, pressing again does nothing
+        let rising = simulation.player().velocity.y;
+        let events = play(&mut simulation, &mut hold(jump, 5));
+        assert_eq!(jumps(&events), 0);
+        assert!(simulation.player().velocity.y > rising);
+
+        let events = play(&mut simulation, &mut hold(InputFrame::default(), 90));
+        assert!(events.iter().any(|event| matches!(event, GameEvent::Landed { .. })));
+        assert!(simulation.player().on_ground);
+        let events = play(&mut simulation, &mut hold(jump, 1));
+        assert_eq!(jumps(&events), 1);
+    }
 
-*/
\ No newline at end of file
+    #[test]
+    fn fast_fall_falls_faster() {
+        let mut normal = simulation();
+        let mut fast = simulation();
+        // the player starts in the air above the first platform
+        play(&mut normal, &mut hold(InputFrame::default(), 10));
+        play(&mut fast, &mut hold(InputFrame { fast_fall: true, ..Default::default() }, 10));
+        assert!(!fast.player().on_ground);
+        assert!(fast.player().velocity.y > normal.player().velocity.y);
+        assert!(fast.player().position.y > normal.player().position.y);
+    }
+}
@@ -1,54 +1,344 @@
+use std::ops::Index;
+
+// fixed capacity ring buffer, the oldest item is at the front
 pub struct Queue<T> {
-    queue: Vec<T>,
-    items_in_q: usize
+    slots: Vec<Option<T>>,
+    // index of the oldest item
+    head: usize,
+    len: usize
 }
 
 impl<T> Queue<T> {
-    pub fn new(n: usize) -> Self {
+    pub fn new(capacity: usize) -> Self {
+        Queue {
+            slots: (0..capacity).map(|_| None).collect(),
+            head: 0,
+            len: 0
+        }
+    }
 
-        Queue { 
-            queue: Vec::with_capacity(n),
-            items_in_q: 0    
+    // hands the item back if there's no room for it
+    pub fn push(&mut self, x: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(x);
         }
+        let tail = self.slot(self.len);
+        self.slots[tail] = Some(x);
+        self.len += 1;
+        Ok(())
     }
 
-    pub fn push(&mut self, x: T) {
-        self.items_in_q += 1;
-        self.queue.push(x);
+    // when full the oldest item makes room and is returned
+    pub fn push_overwrite(&mut self, x: T) -> Option<T> {
+        if self.capacity() == 0 {
+            return Some(x);
+        }
+        if !self.is_full() {
+            let tail = self.slot(self.len);
+            self.slots[tail] = Some(x);
+            self.len += 1;
+            return None;
+        }
+        let oldest = self.slots[self.head].replace(x);
+        self.head = self.slot(1);
+        oldest
     }
 
-    pub fn pop(&mut self) -> T {
-        self.items_in_q -= 1;
-        self.queue.remove(0)
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        let oldest = self.slots[self.head].take();
+        self.head = self.slot(1);
+        self.len -= 1;
+        oldest
     }
-    
-    pub fn get_items_in_q(&self) -> usize {
-        self.items_in_q
+
+    pub fn peek(&self) -> Option<&T> {
+        self.get(0)
     }
-    
-    pub fn get_length(&self) -> usize {
-        self.queue.len()
+
+    pub fn peek_newest(&self) -> Option<&T> {
+        self.len.checked_sub(1).and_then(|i| self.get(i))
     }
-    
-    pub fn get_at(&self, i: usize) -> Option<&T> {
-        if i < self.items_in_q {
-            self.queue.get(i)
+
+    // i counts from the oldest item
+    pub fn get(&self, i: usize) -> Option<&T> {
+        if i < self.len {
+            self.slots[self.slot(i)].as_ref()
         } else {
             None
         }
     }
 
-    pub fn peek(&self) -> Option<&T> {
-        self.queue.first()
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == self.capacity()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn clear(&mut self) {
+        for slot in &mut self.slots {
+            *slot = None;
+        }
+        self.head = 0;
+        self.len = 0;
+    }
+
+    // oldest to newest
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { queue: self, front: 0, back: self.len }
     }
 
-    fn empty(&self) -> bool {
-        self.queue.is_empty()
+    // where the i-th item from the front lives in slots
+    fn slot(&self, i: usize) -> usize {
+        (self.head + i) % self.capacity()
     }
 }
 
 impl<T: Clone> Queue<T> {
+    // replaces whatever is in the queue with capacity copies of value
     pub fn fill_with(&mut self, value: T) {
-        self.queue = vec![value; self.queue.capacity()];
+        for slot in &mut self.slots {
+            *slot = Some(value.clone());
+        }
+        self.head = 0;
+        self.len = self.capacity();
+    }
+}
+
+impl<T> Index<usize> for Queue<T> {
+    type Output = T;
+
+    fn index(&self, i: usize) -> &T {
+        match self.get(i) {
+            Some(item) => item,
+            None => panic!("index {} out of range for queue of length {}", i, self.len),
+        }
     }
-}
\ No newline at end of file
+}
+
+pub struct Iter<'a, T> {
+    queue: &'a Queue<T>,
+    front: usize,
+    back: usize
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        if self.front == self.back {
+            return None;
+        }
+        self.front += 1;
+        self.queue.get(self.front - 1)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.back - self.front;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        self.queue.get(self.back)
+    }
+}
+
+impl<'a, T> ExactSizeIterator for Iter<'a, T> {}
+
+impl<'a, T> IntoIterator for &'a Queue<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use rand::{Rng, SeedableRng};
+    use rand_pcg::Pcg32;
+
+    use super::Queue;
+
+    fn contents(queue: &Queue<i32>) -> Vec<i32> {
+        queue.iter().copied().collect()
+    }
+
+    #[test]
+    fn push_and_pop_keep_order_across_the_wrap() {
+        let mut queue = Queue::new(3);
+        for i in 0..3 {
+            queue.push(i).unwrap();
+        }
+        assert_eq!(queue.pop(), Some(0));
+        assert_eq!(queue.pop(), Some(1));
+        // these two land at the start of the slots, before the oldest item
+        queue.push(3).unwrap();
+        queue.push(4).unwrap();
+        assert_eq!(contents(&queue), vec![2, 3, 4]);
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.pop(), Some(3));
+        assert_eq!(queue.pop(), Some(4));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn push_hands_the_item_back_when_full() {
+        let mut queue = Queue::new(2);
+        assert_eq!(queue.push(1), Ok(()));
+        assert_eq!(queue.push(2), Ok(()));
+        assert!(queue.is_full());
+        assert_eq!(queue.push(3), Err(3));
+        assert_eq!(contents(&queue), vec![1, 2]);
+    }
+
+    #[test]
+    fn push_overwrite_drops_the_oldest() {
+        let mut queue = Queue::new(3);
+        assert_eq!(queue.push_overwrite(1), None);
+        assert_eq!(queue.push_overwrite(2), None);
+        assert_eq!(queue.push_overwrite(3), None);
+        assert_eq!(queue.push_overwrite(4), Some(1));
+        assert_eq!(queue.push_overwrite(5), Some(2));
+        assert_eq!(contents(&queue), vec![3, 4, 5]);
+        assert_eq!(queue.peek(), Some(&3));
+        assert_eq!(queue.peek_newest(), Some(&5));
+        assert_eq!(queue.len(), 3);
+    }
+
+    #[test]
+    fn index_counts_from_the_oldest() {
+        let mut queue = Queue::new(2);
+        for i in 0..5 {
+            queue.push_overwrite(i);
+        }
+        assert_eq!(queue[0], 3);
+        assert_eq!(queue[1], 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn index_past_the_end_panics() {
+        let mut queue = Queue::new(4);
+        queue.push(1).unwrap();
+        let _ = queue[1];
+    }
+
+    #[test]
+    fn fill_with_replaces_everything() {
+        let mut queue = Queue::new(3);
+        queue.push(1).unwrap();
+        queue.pop();
+        queue.push(2).unwrap();
+        queue.fill_with(7);
+        assert!(queue.is_full());
+        assert_eq!(contents(&queue), vec![7, 7, 7]);
+        assert_eq!(queue.push_overwrite(8), Some(7));
+        assert_eq!(contents(&queue), vec![7, 7, 8]);
+    }
+
+    #[test]
+    fn capacity_zero_holds_nothing() {
+        let mut queue = Queue::new(0);
+        assert!(queue.is_empty());
+        assert!(queue.is_full());
+        assert_eq!(queue.push(1), Err(1));
+        assert_eq!(queue.push_overwrite(2), Some(2));
+        queue.fill_with(3);
+        assert_eq!(queue.len(), 0);
+        assert_eq!(queue.pop(), None);
+        assert_eq!(queue.peek(), None);
+        assert_eq!(queue.peek_newest(), None);
+        assert_eq!(queue.iter().next(), None);
+    }
+
+    #[test]
+    fn iter_goes_both_ways_and_knows_its_length() {
+        let mut queue = Queue::new(4);
+        for i in 0..6 {
+            queue.push_overwrite(i);
+        }
+        let mut iter = queue.iter();
+        assert_eq!(iter.size_hint(), (4, Some(4)));
+        assert_eq!(iter.next(), Some(&2));
+        assert_eq!(iter.next_back(), Some(&5));
+        assert_eq!(iter.len(), 2);
+        assert_eq!(iter.next_back(), Some(&4));
+        assert_eq!(iter.next(), Some(&3));
+        assert_eq!(iter.size_hint(), (0, Some(0)));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+        assert_eq!(queue.iter().rev().copied().collect::<Vec<_>>(), vec![5, 4, 3, 2]);
+        assert_eq!((&queue).into_iter().count(), 4);
+    }
+
+    // random sequences of operations checked against VecDeque doing the same thing
+    #[test]
+    fn behaves_like_a_vecdeque() {
+        for seed in 0..200 {
+            let mut rng = Pcg32::seed_from_u64(seed);
+            let capacity = rng.gen_range(0..8);
+            let mut queue = Queue::new(capacity);
+            let mut model = VecDeque::new();
+            for step in 0..500 {
+                match rng.gen_range(0..5) {
+                    0 | 1 => {
+                        let expected = if model.len() < capacity {
+                            model.push_back(step);
+                            Ok(())
+                        } else {
+                            Err(step)
+                        };
+                        assert_eq!(queue.push(step), expected, "seed {}", seed);
+                    }
+                    2 => {
+                        let expected = if capacity == 0 {
+                            Some(step)
+                        } else {
+                            model.push_back(step);
+                            if model.len() > capacity { model.pop_front() } else { None }
+                        };
+                        assert_eq!(queue.push_overwrite(step), expected, "seed {}", seed);
+                    }
+                    3 => assert_eq!(queue.pop(), model.pop_front(), "seed {}", seed),
+                    _ => {
+                        if rng.gen_bool(0.05) {
+                            queue.clear();
+                            model.clear();
+                        }
+                    }
+                }
+                assert_eq!(queue.len(), model.len(), "seed {}", seed);
+                assert_eq!(queue.is_full(), model.len() == capacity, "seed {}", seed);
+                assert_eq!(queue.peek(), model.front(), "seed {}", seed);
+                assert_eq!(queue.peek_newest(), model.back(), "seed {}", seed);
+                assert!(queue.iter().eq(model.iter()), "seed {}", seed);
+                assert!(queue.iter().rev().eq(model.iter().rev()), "seed {}", seed);
+                for i in 0..capacity + 1 {
+                    assert_eq!(queue.get(i), model.get(i), "seed {}", seed);
+                }
+            }
+        }
+    }
+}
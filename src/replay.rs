@@ -0,0 +1,299 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::difficulty::Difficulty;
+use super::flock_config::FlockConfig;
+use super::level_map::LevelMap;
+use super::simulation::InputFrame;
+
+// file layout: magic, version, seed (u64 little endian), tick rate (f64 little endian),
+// the run config's length (u32 little endian) and the run config as json,
+// then two bytes per tick: button bits and the run axis as an i8
+const MAGIC: &[u8; 4] = b"TRPL";
+const VERSION: u8 = 4;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 8 + 8 + 4;
+
+const FRAME_LEN: usize = 2;
+
+const JUMP: u8 = 1 << 0;
+const FAST_FALL: u8 = 1 << 1;
+const RESTART: u8 = 1 << 2;
+
+fn encode_frame(frame: &InputFrame) -> [u8; FRAME_LEN] {
+    let mut bits = 0;
+    for (down, bit) in [
+        (frame.jump, JUMP),
+        (frame.fast_fall, FAST_FALL),
+        (frame.restart, RESTART),
+    ] {
+        if down {
+            bits |= bit;
+        }
+    }
+    [bits, frame.run.to_le_bytes()[0]]
+}
+
+fn decode_frame(bytes: &[u8]) -> InputFrame {
+    let bits = bytes[0];
+    InputFrame {
+        run: i8::from_le_bytes([bytes[1]]),
+        jump: bits & JUMP != 0,
+        fast_fall: bits & FAST_FALL != 0,
+        restart: bits & RESTART != 0,
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// everything besides the seed and the input that a run depends on. a replay keeps its own copy,
+// so editing the config files or leaving off --level doesn't change what plays back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunConfig {
+    pub flock_config: FlockConfig,
+    pub difficulty: Difficulty,
+    pub level: Option<LevelMap>,
+}
+
+// appends each tick's input to a replay file as the game runs
+pub struct ReplayWriter {
+    out: BufWriter<File>,
+}
+
+impl ReplayWriter {
+    pub fn create<P: AsRef<Path>>(path: P, seed: u64, tick_rate: f64, config: &RunConfig) -> io::Result<ReplayWriter> {
+        let config = serde_json::to_vec(config)?;
+        let config_len = u32::try_from(config.len()).map_err(|_| invalid_data("run config is too big for a replay"))?;
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        out.write_all(&seed.to_le_bytes())?;
+        out.write_all(&tick_rate.to_le_bytes())?;
+        out.write_all(&config_len.to_le_bytes())?;
+        out.write_all(&config)?;
+        Ok(ReplayWriter { out })
+    }
+
+    pub fn record(&mut self, frame: &InputFrame) -> io::Result<()> {
+        self.out.write_all(&encode_frame(frame))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Replay {
+    seed: u64,
+    tick_rate: f64,
+    config: RunConfig,
+    frames: Vec<InputFrame>,
+}
+
+impl Replay {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Replay> {
+        let mut bytes = Vec::new();
+        BufReader::new(File::open(path)?).read_to_end(&mut bytes)?;
+
+        if bytes.len() < MAGIC.len() + 1 || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(invalid_data("not a replay file"));
+        }
+        if bytes[MAGIC.len()] != VERSION {
+            return Err(invalid_data("unsupported replay version"));
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(invalid_data("replay header is truncated"));
+        }
+        let mut seed = [0; 8];
+        seed.copy_from_slice(&bytes[MAGIC.len() + 1..MAGIC.len() + 9]);
+        let mut tick_rate = [0; 8];
+        tick_rate.copy_from_slice(&bytes[MAGIC.len() + 9..MAGIC.len() + 17]);
+        let tick_rate = f64::from_le_bytes(tick_rate);
+        if !tick_rate.is_finite() || tick_rate <= 0.0 {
+            return Err(invalid_data("replay has an invalid tick rate"));
+        }
+        let mut config_len = [0; 4];
+        config_len.copy_from_slice(&bytes[MAGIC.len() + 17..HEADER_LEN]);
+        let config_end = HEADER_LEN + u32::from_le_bytes(config_len) as usize;
+        if bytes.len() < config_end {
+            return Err(invalid_data("replay header is truncated"));
+        }
+        let config: RunConfig = serde_json::from_slice(&bytes[HEADER_LEN..config_end])
+            .map_err(|e| invalid_data(&format!("replay has an invalid run config: {}", e)))?;
+        config
+            .difficulty
+            .validate()
+            .map_err(|e| invalid_data(&format!("replay has an invalid difficulty table: {}", e)))?;
+        let frame_bytes = &bytes[config_end..];
+        if frame_bytes.len() % FRAME_LEN != 0 {
+            return Err(invalid_data("replay ends partway through a tick"));
+        }
+
+        Ok(Replay {
+            seed: u64::from_le_bytes(seed),
+            tick_rate,
+            config,
+            frames: frame_bytes.chunks_exact(FRAME_LEN).map(decode_frame).collect(),
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn tick_rate(&self) -> f64 {
+        self.tick_rate
+    }
+
+    // what the run was recorded with, used in place of whatever the config files say now
+    pub fn config(&self) -> &RunConfig {
+        &self.config
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn frame(&self, tick: usize) -> Option<&InputFrame> {
+        self.frames.get(tick)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io;
+    use std::path::PathBuf;
+
+    use tetra::math::Vec2;
+
+    use crate::collision::Aabb;
+    use crate::difficulty::Difficulty;
+    use crate::flock_config::FlockConfig;
+    use crate::level_map::{BatZone, LevelMap};
+    use crate::simulation::InputFrame;
+
+    use super::{decode_frame, encode_frame, Replay, ReplayWriter, RunConfig, HEADER_LEN, MAGIC, VERSION};
+
+    // somewhere of its own under the temp dir, so tests running at once don't share files
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("tetras-{}-{}.replay", name, std::process::id()))
+    }
+
+    fn run_config() -> RunConfig {
+        RunConfig {
+            flock_config: FlockConfig { max_speed: 123.456, ..FlockConfig::default() },
+            difficulty: Difficulty::constant(7),
+            level: Some(LevelMap {
+                platforms: vec![Aabb::new(Vec2::new(0.0, 420.0), Vec2::new(640.0, 20.0))],
+                bricks: vec![Aabb::new(Vec2::new(16.0, 0.1), Vec2::new(16.0, 16.0))],
+                player_spawn: Vec2::new(100.0, 1.0 / 3.0),
+                tokens: vec![Vec2::new(500.0, 300.0)],
+                bat_zones: vec![BatZone { area: Aabb::new(Vec2::new(0.0, 0.0), Vec2::new(64.0, 64.0)), count: 3 }],
+                bounds: Aabb::new(Vec2::new(0.0, 0.0), Vec2::new(640.0, 480.0)),
+            }),
+        }
+    }
+
+    // playback has to run on exactly what was recorded, not on whatever is on disk by then
+    #[test]
+    fn keeps_the_run_config() {
+        let path = temp_path("run-config");
+        let config = run_config();
+        let mut writer = ReplayWriter::create(&path, 42, 60.0, &config).unwrap();
+        writer.record(&InputFrame::default()).unwrap();
+        drop(writer);
+
+        let replay = Replay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(replay.seed(), 42);
+        assert_eq!(replay.tick_rate(), 60.0);
+        assert_eq!(*replay.config(), config);
+        assert_eq!(replay.len(), 1);
+    }
+
+    // every combination of buttons at both ends of the run axis and in the middle
+    fn every_frame() -> Vec<InputFrame> {
+        let mut frames = Vec::new();
+        for run in [-127, 0, 127] {
+            for bits in 0..8 {
+                frames.push(InputFrame {
+                    run,
+                    jump: bits & 1 != 0,
+                    fast_fall: bits & 2 != 0,
+                    restart: bits & 4 != 0,
+                });
+            }
+        }
+        frames
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        for frame in every_frame() {
+            assert_eq!(decode_frame(&encode_frame(&frame)), frame);
+        }
+
+        let path = temp_path("frames");
+        let mut writer = ReplayWriter::create(&path, u64::MAX, 144.0, &run_config()).unwrap();
+        for frame in every_frame() {
+            writer.record(&frame).unwrap();
+        }
+        drop(writer);
+
+        let replay = Replay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(replay.seed(), u64::MAX);
+        assert_eq!(replay.tick_rate(), 144.0);
+        let frames: Vec<InputFrame> = (0..replay.len()).map(|tick| *replay.frame(tick).unwrap()).collect();
+        assert_eq!(frames, every_frame());
+        assert_eq!(replay.frame(replay.len()), None);
+    }
+
+    // a good replay's bytes, broken by change, then loaded again
+    fn load_broken(name: &str, change: impl FnOnce(&mut Vec<u8>)) -> io::Result<Replay> {
+        let path = temp_path(name);
+        let mut writer = ReplayWriter::create(&path, 7, 60.0, &run_config()).unwrap();
+        for frame in every_frame() {
+            writer.record(&frame).unwrap();
+        }
+        drop(writer);
+
+        let mut bytes = fs::read(&path).unwrap();
+        change(&mut bytes);
+        fs::write(&path, bytes).unwrap();
+        let replay = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+        replay
+    }
+
+    fn assert_invalid(name: &str, change: impl FnOnce(&mut Vec<u8>)) {
+        match load_broken(name, change) {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidData, "{}: {}", name, e),
+            Ok(_) => panic!("{}: loaded a broken replay", name),
+        }
+    }
+
+    #[test]
+    fn broken_replays_are_invalid_data() {
+        assert!(load_broken("unchanged", |_| {}).is_ok());
+
+        assert_invalid("empty", |bytes| bytes.clear());
+        assert_invalid("truncated-header", |bytes| bytes.truncate(MAGIC.len() + 1 + 8));
+        assert_invalid("truncated-config", |bytes| bytes.truncate(HEADER_LEN + 3));
+        assert_invalid("config", |bytes| bytes[HEADER_LEN] = b'!');
+        assert_invalid("magic", |bytes| bytes[0] = b'X');
+        assert_invalid("version", |bytes| bytes[MAGIC.len()] = VERSION - 1);
+        assert_invalid("odd-frames", |bytes| bytes.push(0));
+        for (name, tick_rate) in [("nan", f64::NAN), ("infinite", f64::INFINITY), ("zero", 0.0)] {
+            assert_invalid(name, |bytes| {
+                bytes[MAGIC.len() + 9..MAGIC.len() + 17].copy_from_slice(&tick_rate.to_le_bytes())
+            });
+        }
+    }
+}
@@ -0,0 +1,46 @@
+// which screen the game is on. the top of the stack gets the input, everything is drawn bottom up
+// so a pause screen can sit on top of the game it paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scene {
+    Title,
+    Controls,
+    Playing,
+    Paused,
+    GameOver { score: u32 },
+}
+
+pub struct SceneStack {
+    scenes: Vec<Scene>,
+}
+
+impl SceneStack {
+    pub fn new(first: Scene) -> SceneStack {
+        SceneStack { scenes: vec![first] }
+    }
+
+    pub fn current(&self) -> Scene {
+        // never empty, pop won't take the last one
+        self.scenes[self.scenes.len() - 1]
+    }
+
+    pub fn push(&mut self, scene: Scene) {
+        self.scenes.push(scene);
+    }
+
+    pub fn pop(&mut self) {
+        if self.scenes.len() > 1 {
+            self.scenes.pop();
+        }
+    }
+
+    // drops everything underneath too, for moving on rather than layering
+    pub fn replace(&mut self, scene: Scene) {
+        self.scenes.clear();
+        self.scenes.push(scene);
+    }
+
+    // bottom to top
+    pub fn iter(&self) -> impl Iterator<Item = &Scene> {
+        self.scenes.iter()
+    }
+}
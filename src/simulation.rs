@@ -0,0 +1,558 @@
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use tetra::math::Vec2;
+
+use super::camera::Camera;
+use super::collision::Aabb;
+use super::difficulty::Difficulty;
+use super::enemy::{Enemy, FlockView};
+use super::flock_config::FlockConfig;
+use super::game_object::{GameObject, ObjectKind, World};
+use super::level_map::LevelMap;
+use super::platform::{Platform, PlatformStyle};
+use super::platform_generator::PlatformGenerator;
+use super::player::Player;
+use super::spatial_hash::SpatialHash;
+use super::token::Token;
+use super::{PLATFORM_NUM, TOKEN_NUM, WINDOW_HEIGHT, WINDOW_WIDTH};
+
+// the camera follows the player up once they're this close to the top of the view
+const DEAD_ZONE_TOP: f32 = 32.0;
+// screen shake for every second a bat is touching the player, and for dying
+const HURT_TRAUMA: f32 = 1.5;
+const DEATH_TRAUMA: f32 = 0.8;
+
+// what the player is doing for a single tick, sampled by whoever drives the simulation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputFrame {
+    // -RUN_MAX is full speed left, RUN_MAX full speed right. whole steps so a replay stores it exactly
+    pub run: i8,
+    pub jump: bool,
+    pub fast_fall: bool,
+    pub restart: bool,
+}
+
+impl InputFrame {
+    pub const RUN_MAX: i8 = 127;
+
+    // an analog amount from -1 to 1 rounded to the nearest step
+    pub fn run_from(amount: f32) -> i8 {
+        (amount.clamp(-1.0, 1.0) * InputFrame::RUN_MAX as f32).round() as i8
+    }
+
+    // -1 to 1
+    pub fn run_amount(&self) -> f32 {
+        self.run as f32 / InputFrame::RUN_MAX as f32
+    }
+
+    // two devices at once: buttons on either count, and the harder push on the stick wins
+    pub fn merge(self, other: InputFrame) -> InputFrame {
+        InputFrame {
+            run: if other.run.unsigned_abs() > self.run.unsigned_abs() { other.run } else { self.run },
+            jump: self.jump || other.jump,
+            fast_fall: self.fast_fall || other.fast_fall,
+            restart: self.restart || other.restart,
+        }
+    }
+}
+
+// something that happened during a tick that the simulation has to react to afterwards. they're
+// kept until the next tick so whoever is drawing the game can show them too
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameEvent {
+    TokenCollected { index: usize, position: Vec2<f32> },
+    // a bat touched the player this tick
+    PlayerHurt { position: Vec2<f32> },
+    PlayerDied { position: Vec2<f32> },
+    Jumped { position: Vec2<f32> },
+    // touched down on top of a platform after being off the ground
+    Landed { position: Vec2<f32> },
+}
+
+// every random decision in a run goes through one of these so a seed reproduces the run
+pub type GameRng = Pcg32;
+
+// all of the gameplay state, steppable without a window or a tetra::Context
+pub struct Simulation {
+    player: Player,
+    platforms: Vec<Platform>,
+    enemies: Vec<Enemy>,
+    tokens: Vec<Token>,
+    platform_generator: PlatformGenerator,
+    camera: Camera,
+    // how fast the camera scrolls up, in pixels per second
+    down_speed: f32,
+    seed: u64,
+    rng: GameRng,
+    tick_rate: f64,
+    dt: f32,
+    // steps since this world was built
+    ticks: u64,
+    // how far the camera has climbed, in pixels
+    distance: f32,
+    // as loaded, flock_config is this with the current level's multipliers applied
+    base_flock_config: FlockConfig,
+    flock_config: FlockConfig,
+    difficulty: Difficulty,
+    level: usize,
+    next_enemy_id: u16,
+    // set when playing a hand made level, which keeps its own platforms and swarm size
+    map: Option<LevelMap>,
+    grid: SpatialHash,
+    events: Vec<GameEvent>,
+}
+
+impl Simulation {
+    // every call to step advances the game by 1 / tick_rate seconds. without a map the layout is random
+    pub fn new(seed: u64, flock_config: FlockConfig, difficulty: Difficulty, tick_rate: f64, map: Option<LevelMap>) -> Simulation {
+        let mut rng = GameRng::seed_from_u64(seed);
+        let platform_generator = PlatformGenerator::new(Player::jump_envelope(), Vec2::new(78.0,50.0));
+        let size = Vec2::new(WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32);
+        let dead_zone = Aabb::new(Vec2::new(0.0, DEAD_ZONE_TOP), size - Vec2::new(0.0, DEAD_ZONE_TOP * 2.0));
+        let camera = Camera::new(Vec2::new(0.0, 0.0), size, dead_zone, map.as_ref().map(|map| map.bounds));
+        let (player, platforms, tokens, enemies) = match &map {
+            Some(map) => Simulation::designed_layout(map, tick_rate, &mut rng),
+            None => Simulation::random_layout(&platform_generator, &camera.view(), difficulty.level(0).bats, tick_rate, &mut rng),
+        };
+        let next_available_id = enemies.len() as u16;
+
+        let mut simulation = Simulation {
+            player,
+            platforms,
+            enemies,
+            tokens,
+            platform_generator,
+            camera,
+            down_speed: 0.0,
+            seed,
+            rng,
+            tick_rate,
+            dt: (1.0 / tick_rate) as f32,
+            ticks: 0,
+            distance: 0.0,
+            grid: SpatialHash::new(flock_config.visual_range),
+            base_flock_config: flock_config.clone(),
+            flock_config,
+            difficulty,
+            level: 0,
+            next_enemy_id: next_available_id,
+            map,
+            events: Vec::new(),
+        };
+        simulation.apply_level();
+        simulation
+    }
+
+    fn random_layout(platform_generator: &PlatformGenerator, view: &Aabb, enemy_num: usize, tick_rate: f64, rng: &mut GameRng) -> (Player, Vec<Platform>, Vec<Token>, Vec<Enemy>) {
+        let start_pos = Vec2::new(32.0, 32.0);
+        let player = Player::new(start_pos, tick_rate);
+
+        let mut platforms = Vec::with_capacity(PLATFORM_NUM);
+        let mut enemies = Vec::with_capacity(enemy_num);
+        let mut tokens: Vec<Token> = Vec::with_capacity(TOKEN_NUM);
+        let mut next_available_id: u16 = 0;
+
+        // first platform under player, everything else has to be reachable from it
+        let platform_size = Vec2::new(78.0,50.0);
+        let start_platform = Aabb::new(Vec2::new(0.0, 148.0), platform_size);
+        // the player drops onto it from start_pos, so keep that clear
+        let drop_zone = Aabb::new(Vec2::new(0.0, 0.0), Vec2::new(platform_size.x, start_platform.min().y));
+        let mut placed = vec![start_platform];
+        for _ in 1..PLATFORM_NUM {
+            // anything that doesn't fit waits below the screen to be spawned in at the top later
+            let position = platform_generator
+                .place(&placed, &[drop_zone], 37.0, WINDOW_HEIGHT as f32 - platform_size.y, view, rng)
+                .unwrap_or(Vec2::new(0.0, WINDOW_HEIGHT as f32));
+            placed.push(Aabb::new(position, platform_size));
+        }
+        platforms.extend(placed.iter().map(|bounds| Platform::new(bounds.size, bounds.position, PlatformStyle::Generated)));
+
+        for _ in 0..TOKEN_NUM {
+            tokens.push(Token::new(Vec2::new(15.0,16.0), true, Vec2::new(0.0,0.0), rng));
+        }
+
+        for _ in 0..enemy_num.saturating_sub(1) {
+            enemies.push(Enemy::new(next_available_id, Vec2::new(20.0,20.0), rng, false));
+            next_available_id += 1;
+        }
+        // set debug to true for one bat's debug console out
+        if enemy_num > 0 {
+            enemies.push(Enemy::new(next_available_id, Vec2::new(20.0,20.0), rng, true));
+        }
+        (player, platforms, tokens, enemies)
+    }
+
+    fn designed_layout(map: &LevelMap, tick_rate: f64, rng: &mut GameRng) -> (Player, Vec<Platform>, Vec<Token>, Vec<Enemy>) {
+        let player = Player::new(map.player_spawn, tick_rate);
+
+        let platforms = map.platforms.iter()
+            .map(|tile| Platform::new(tile.size, tile.position, PlatformStyle::Tile))
+            .chain(map.bricks.iter().map(|tile| Platform::new(tile.size, tile.position, PlatformStyle::Brick)))
+            .collect();
+
+        let tokens = map.tokens.iter()
+            .map(|&position| Token::new(Vec2::new(15.0,16.0), false, position, rng))
+            .collect();
+
+        let mut enemies = Vec::with_capacity(map.bats());
+        for zone in &map.bat_zones {
+            for _ in 0..zone.count {
+                let id = enemies.len() as u16;
+                enemies.push(Enemy::spawn_in(id, Vec2::new(20.0,20.0), &zone.area, rng, false));
+            }
+        }
+        (player, platforms, tokens, enemies)
+    }
+
+    pub fn step(&mut self, inputs: &InputFrame) {
+        if inputs.restart {
+            self.restart();
+            return;
+        }
+        self.events.clear();
+
+        let boids_id: Vec<u16> = self.enemies.iter().map(Enemy::get_id).collect();
+        let boids_pos: Vec<Vec2<f32>> = self.enemies.iter().map(Enemy::get_pos).collect();
+        let boids_vel: Vec<Vec2<f32>> = self.enemies.iter().map(Enemy::get_vel).collect();
+        let flock = FlockView {
+            ids: &boids_id,
+            positions: &boids_pos,
+            velocities: &boids_vel,
+        };
+        self.grid.rebuild(&boids_pos);
+
+        let mut platforms = Vec::with_capacity(self.platforms.len());
+        let mut tokens = Vec::with_capacity(self.tokens.len());
+        let mut enemies = Vec::with_capacity(self.enemies.len());
+        for object in self.objects() {
+            match object.kind() {
+                ObjectKind::Platform => platforms.push(object.bounds()),
+                ObjectKind::Token => tokens.push(object.bounds()),
+                ObjectKind::Enemy => enemies.push(object.bounds()),
+                ObjectKind::Player => {}
+            }
+        }
+
+        let view = self.camera.view();
+        let world = World {
+            dt: self.dt,
+            view,
+            inputs,
+            player_trail: self.player.oldest_player_pos(&view),
+            platforms: &platforms,
+            tokens: &tokens,
+            enemies: &enemies,
+            flock: &flock,
+            flock_grid: &self.grid,
+            flock_config: &self.flock_config,
+        };
+
+        let objects = self.platforms.iter_mut().map(|o| o as &mut dyn GameObject)
+            .chain(self.tokens.iter_mut().map(|o| o as &mut dyn GameObject))
+            .chain(self.enemies.iter_mut().map(|o| o as &mut dyn GameObject))
+            .chain(std::iter::once(&mut self.player as &mut dyn GameObject));
+        for object in objects {
+            object.update(&world, &mut self.rng, &mut self.events);
+        }
+
+        // a hand made level's tokens are used up rather than coming back
+        let mut used_up = Vec::new();
+        for event in &self.events {
+            match *event {
+                GameEvent::TokenCollected { index, .. } if self.map.is_some() => used_up.push(index),
+                GameEvent::TokenCollected { index, .. } => self.tokens[index].respawn(&view, &mut self.rng),
+                GameEvent::PlayerHurt { .. } => self.camera.add_trauma(HURT_TRAUMA * self.dt),
+                GameEvent::PlayerDied { .. } => self.camera.add_trauma(DEATH_TRAUMA),
+                GameEvent::Jumped { .. } | GameEvent::Landed { .. } => {}
+            }
+        }
+        // highest index first so the rest stay where they are
+        used_up.sort_unstable();
+        for index in used_up.into_iter().rev() {
+            self.tokens.remove(index);
+        }
+
+        let top = view.min().y;
+        self.camera.update(self.player.bounds().center(), self.down_speed * self.dt, self.dt);
+        self.distance += top - self.camera.view().min().y;
+        self.respawn_platforms();
+
+        self.ticks += 1;
+
+        let level = self.difficulty.level_for(self.level, self.elapsed(), self.player.get_score());
+        if level != self.level {
+            self.level = level;
+            self.apply_level();
+        }
+    }
+
+    // throws the whole world away and builds a fresh one. the new seed comes from the old rng,
+    // so a replay that restarts still plays back the same way
+    pub fn restart(&mut self) {
+        let seed = self.rng.gen();
+        let map = self.map.take();
+        *self = Simulation::new(seed, self.base_flock_config.clone(), self.difficulty.clone(), self.tick_rate, map);
+    }
+
+    // platforms the view has left behind come back in above it, as soon as there's somewhere
+    // for them that the player can get to
+    fn respawn_platforms(&mut self) {
+        // a hand made level is climbed once
+        if self.map.is_some() {
+            return;
+        }
+        let view = self.camera.view();
+        let mut bounds: Vec<Aabb> = self.platforms.iter().map(GameObject::bounds).collect();
+        for i in 0..self.platforms.len() {
+            if PlatformGenerator::in_play(&bounds[i], &view) {
+                continue;
+            }
+            if let Some(position) = self.platform_generator.spawn(&bounds, &view, &mut self.rng) {
+                self.platforms[i].respawn(position);
+                bounds[i] = self.platforms[i].bounds();
+            }
+        }
+    }
+
+    // brings speeds and the size of the swarm in line with the current level
+    fn apply_level(&mut self) {
+        let level = self.difficulty.level(self.level);
+        self.down_speed = level.down_speed;
+        self.flock_config.max_speed = self.base_flock_config.max_speed * level.bat_speed;
+        self.flock_config.player_attraction = self.base_flock_config.player_attraction * level.attraction;
+
+        // a hand made level says how many bats there are itself
+        if self.map.is_some() {
+            return;
+        }
+        let bats = level.bats;
+        self.enemies.truncate(bats);
+        // where Enemy::new puts them at the start of a run, wherever the view has got to
+        let area = self.camera.view().translated(Vec2::new(0.0, -200.0));
+        while self.enemies.len() < bats {
+            self.enemies.push(Enemy::spawn_in(self.next_enemy_id, Vec2::new(20.0,20.0), &area, &mut self.rng, false));
+            self.next_enemy_id = self.next_enemy_id.wrapping_add(1);
+        }
+    }
+
+    // everything in the game, in the order it should be drawn
+    pub fn objects(&self) -> impl Iterator<Item = &dyn GameObject> {
+        self.platforms.iter().map(|o| o as &dyn GameObject)
+            .chain(self.tokens.iter().map(|o| o as &dyn GameObject))
+            .chain(self.enemies.iter().map(|o| o as &dyn GameObject))
+            .chain(std::iter::once(&self.player as &dyn GameObject))
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn tick_rate(&self) -> f64 {
+        self.tick_rate
+    }
+
+    // seconds of game time in this run
+    pub fn elapsed(&self) -> f32 {
+        (self.ticks as f64 / self.tick_rate) as f32
+    }
+
+    // debug only: freezing a bat isn't part of the recorded input, so replays won't see it
+    pub fn set_bat_frozen(&mut self, id: u16, frozen: bool) {
+        if let Some(enemy) = self.enemies.iter_mut().find(|enemy| enemy.get_id() == id) {
+            enemy.set_state(frozen);
+        }
+    }
+
+    // counting from 0, the hud shows it from 1
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    pub fn flock_config(&self) -> &FlockConfig {
+        &self.flock_config
+    }
+
+    pub fn player(&self) -> &Player {
+        &self.player
+    }
+
+    pub fn platforms(&self) -> &[Platform] {
+        &self.platforms
+    }
+
+    pub fn enemies(&self) -> &[Enemy] {
+        &self.enemies
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    // everything that happened in the last step
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InputFrame, Simulation};
+    use crate::collision::Aabb;
+    use crate::difficulty::Difficulty;
+    use crate::flock_config::FlockConfig;
+    use crate::game_object::GameObject;
+    use crate::input_source::{InputSource, ScriptedInput};
+    use crate::level_map::LevelMap;
+    use tetra::math::Vec2;
+
+    fn simulation(seed: u64) -> Simulation {
+        Simulation::new(seed, FlockConfig::default(), Difficulty::default(), 60.0, None)
+    }
+
+    fn assert_sane(simulation: &Simulation) {
+        let health = simulation.player().get_health();
+        assert!((0.0..=100.0).contains(&health), "health {}", health);
+        let player = simulation.player().bounds();
+        assert!(player.position.x.is_finite() && player.position.y.is_finite(), "player at {:?}", player.position);
+        for object in simulation.objects() {
+            let bounds = object.bounds();
+            assert!(
+                bounds.position.x.is_finite() && bounds.position.y.is_finite(),
+                "{:?} at {:?} after {} ticks",
+                object.kind(),
+                bounds.position,
+                simulation.ticks
+            );
+        }
+    }
+
+    #[test]
+    fn idle_player_survives_the_first_seconds() {
+        for seed in 0..5 {
+            let mut simulation = simulation(seed);
+            for _ in 0..300 {
+                simulation.step(&InputFrame::default());
+                assert_sane(&simulation);
+            }
+            assert!(!simulation.player().is_dead(), "seed {} died standing still", seed);
+        }
+    }
+
+    // thousands of ticks of mashing every input, restarting whenever the player dies
+    #[test]
+    fn thousands_of_ticks_stay_sane() {
+        let mut simulation = simulation(42);
+        for tick in 0..3000 {
+            let frame = InputFrame {
+                run: [InputFrame::RUN_MAX, 0, -InputFrame::RUN_MAX][tick / 40 % 3],
+                jump: tick % 25 == 0,
+                fast_fall: tick % 90 > 80,
+                restart: simulation.player().is_dead(),
+            };
+            simulation.step(&frame);
+            assert_sane(&simulation);
+        }
+    }
+
+    // every platform, token and bat, in order
+    fn snapshot(simulation: &Simulation) -> (Vec<Aabb>, Vec<Aabb>, Vec<Aabb>) {
+        (
+            simulation.platforms().iter().map(GameObject::bounds).collect(),
+            simulation.tokens().iter().map(GameObject::bounds).collect(),
+            simulation.enemies().iter().map(GameObject::bounds).collect(),
+        )
+    }
+
+    fn script() -> ScriptedInput {
+        let run = |run| InputFrame { run, ..Default::default() };
+        ScriptedInput::new(Vec::new())
+            .hold(InputFrame::default(), 60)
+            .hold(run(InputFrame::RUN_MAX), 40)
+            .hold(InputFrame { jump: true, ..Default::default() }, 1)
+            .hold(run(-InputFrame::RUN_MAX / 2), 80)
+            .hold(InputFrame { fast_fall: true, ..Default::default() }, 30)
+            .hold(InputFrame { restart: true, ..Default::default() }, 1)
+            .hold(run(InputFrame::RUN_MAX), 200)
+    }
+
+    // what replays depend on: the same seed and inputs play out exactly the same
+    #[test]
+    fn same_seed_and_inputs_give_the_same_run() {
+        let mut a = simulation(7);
+        let mut b = simulation(7);
+        assert_eq!(snapshot(&a), snapshot(&b));
+        let (mut script_a, mut script_b) = (script(), script());
+        while !script_a.is_finished() {
+            a.step(&script_a.next_frame());
+            b.step(&script_b.next_frame());
+            assert_eq!(snapshot(&a), snapshot(&b), "tick {}", a.ticks);
+            assert_eq!(a.player().bounds(), b.player().bounds(), "tick {}", a.ticks);
+            assert_eq!(a.events(), b.events(), "tick {}", a.ticks);
+        }
+        assert_eq!(a.seed(), b.seed());
+    }
+
+    #[test]
+    fn different_seeds_diverge() {
+        let mut a = simulation(7);
+        let mut b = simulation(8);
+        assert_ne!(snapshot(&a).0, snapshot(&b).0);
+        for _ in 0..60 {
+            a.step(&InputFrame::default());
+            b.step(&InputFrame::default());
+        }
+        let (a, b) = (snapshot(&a), snapshot(&b));
+        assert_ne!(a.1, b.1);
+        assert_ne!(a.2, b.2);
+    }
+
+    // a token in a hand made level is picked up once and then it's gone
+    #[test]
+    fn map_tokens_are_used_up() {
+        let spawn = Vec2::new(100.0, 400.0);
+        let map = LevelMap {
+            platforms: vec![Aabb::new(Vec2::new(0.0, 420.0), Vec2::new(640.0, 20.0))],
+            bricks: Vec::new(),
+            player_spawn: spawn,
+            tokens: vec![spawn, Vec2::new(500.0, 300.0)],
+            bat_zones: Vec::new(),
+            bounds: Aabb::new(Vec2::new(0.0, 0.0), Vec2::new(640.0, 480.0)),
+        };
+        let mut simulation = Simulation::new(1, FlockConfig::default(), Difficulty::constant(0), 60.0, Some(map));
+        simulation.step(&InputFrame::default());
+        assert_eq!(simulation.player().get_score(), 1);
+        assert_eq!(simulation.tokens().len(), 1);
+        assert_eq!(simulation.tokens()[0].bounds().position, Vec2::new(500.0, 300.0));
+        for _ in 0..600 {
+            simulation.step(&InputFrame::default());
+        }
+        assert_eq!(simulation.tokens().len(), 1);
+        assert_eq!(simulation.tokens()[0].bounds().position, Vec2::new(500.0, 300.0));
+    }
+
+    // setting it twice leaves it set, and a restart brings every bat back unfrozen
+    #[test]
+    fn freezing_a_bat_is_explicit() {
+        let mut simulation = simulation(3);
+        let id = simulation.enemies()[0].get_id();
+        let is_frozen = |simulation: &Simulation| simulation.enemies().iter().any(|enemy| enemy.get_id() == id && enemy.is_frozen());
+
+        simulation.set_bat_frozen(id, true);
+        simulation.set_bat_frozen(id, true);
+        assert!(is_frozen(&simulation));
+        simulation.set_bat_frozen(id, false);
+        assert!(!is_frozen(&simulation));
+
+        simulation.set_bat_frozen(id, true);
+        simulation.step(&InputFrame { restart: true, ..InputFrame::default() });
+        assert!(simulation.enemies().iter().all(|enemy| !enemy.is_frozen()));
+    }
+}
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+
+use tetra::math::Vec2;
+
+// uniform grid of buckets so neighbour lookups only look at nearby cells instead of every boid
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> SpatialHash {
+        SpatialHash {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell_of(&self, pos: Vec2<f32>) -> (i32, i32) {
+        ((pos.x / self.cell_size).floor() as i32, (pos.y / self.cell_size).floor() as i32)
+    }
+
+    // buckets every position by index; the bucket vecs are kept around so rebuilding each tick doesn't allocate
+    pub fn rebuild(&mut self, positions: &[Vec2<f32>]) {
+        self.cells.retain(|_, bucket| !bucket.is_empty());
+        for bucket in self.cells.values_mut() {
+            bucket.clear();
+        }
+        for (i, &pos) in positions.iter().enumerate() {
+            let cell = self.cell_of(pos);
+            self.cells.entry(cell).or_default().push(i);
+        }
+    }
+
+    // pushes the index of everything in a cell touching the circle, callers still need to check the distance
+    pub fn query(&self, center: Vec2<f32>, radius: f32, out: &mut Vec<usize>) {
+        out.clear();
+        let (min_x, min_y) = self.cell_of(center - Vec2::new(radius, radius));
+        let (max_x, max_y) = self.cell_of(center + Vec2::new(radius, radius));
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                if let Some(bucket) = self.cells.get(&(x, y)) {
+                    out.extend_from_slice(bucket);
+                }
+            }
+        }
+    }
+}
@@ -1,58 +1,61 @@
-use tetra::graphics::{Texture};
 use tetra::math::Vec2;
 use tetra::Context;
-use rand::rngs::ThreadRng;
-use rand::{self, Rng};
+use rand::Rng;
+
+use super::collision::Aabb;
+use super::game_object::{GameObject, ObjectKind, Textures, World};
+use super::simulation::{GameEvent, GameRng};
 
 
 #[derive(Debug, Clone)]
 pub struct Token {
-    texture: Texture,
     position: Vec2<f32>,
     dimension: Vec2<f32>,
-    rng: ThreadRng
+    // random tokens keep coming back, ones placed in a map are there once
+    respawns: bool,
 }
 
 impl Token {
-    pub fn new(t: Texture, dim: Vec2<f32>, use_random: bool, pos: Vec2<f32>) -> Token {
-        let mut rng = rand::thread_rng();
+    pub fn new(dim: Vec2<f32>, use_random: bool, pos: Vec2<f32>, rng: &mut GameRng) -> Token {
         if use_random {
             let y_pos = ((rng.gen::<f32>() * 42.6).floor()) * 15.0;
             let x_pos = ((rng.gen::<f32>() * 30.0).floor()) * 16.0;
             Token {        
-                texture: t,
                 position: Vec2::new(x_pos,y_pos),
-                dimension: dim,     
-                rng  
+                dimension: dim,
+                respawns: true,
             }
         } else {
             Token {
-                texture: t,
                 position: pos,
                 dimension: dim,
-                rng
+                respawns: false,
             }
         }
     }
 
-    pub fn update(&mut self, down_speed: f32) {
-        self.position.y += down_speed;
-        if self.position.y >= super::WINDOW_HEIGHT as f32 {
-            self.position.y -= super::WINDOW_HEIGHT as f32 + (1.0 * 15.0);
-            self.position.x = ((self.rng.gen::<f32>() * 30.0).floor()) * 16.0;
+    // after being picked up, or being left behind, a random token comes back in just above the view
+    pub fn respawn(&mut self, view: &Aabb, rng: &mut GameRng) {
+        self.position = Vec2::new(((rng.gen::<f32>() * 30.0).floor()) * 16.0, view.min().y - 15.0);
+    }
+}
 
-        }
+impl GameObject for Token {
+    fn kind(&self) -> ObjectKind {
+        ObjectKind::Token
     }
 
-    pub fn draw(&self, ctx: &mut Context) {
-        self.texture.draw(ctx, self.position);
+    fn bounds(&self) -> Aabb {
+        Aabb::new(self.position, self.dimension)
     }
 
-    pub fn get_pos(&self) -> Vec2<f32> {
-        self.position
+    fn update(&mut self, world: &World, rng: &mut GameRng, _events: &mut Vec<GameEvent>) {
+        if self.respawns && self.position.y >= world.view.max().y {
+            self.respawn(&world.view, rng);
+        }
     }
 
-    pub fn get_dim(&self) -> Vec2<f32> {
-        self.dimension
+    fn draw(&self, ctx: &mut Context, textures: &Textures, _blend: f32) {
+        textures.token.draw(ctx, self.position);
     }
-}
\ No newline at end of file
+}